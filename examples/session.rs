@@ -0,0 +1,18 @@
+//! Load the session from `session.json`, print the front page and save the session back.
+
+use std::sync::Arc;
+use ehviewer_x::{eh_client::EhClient, eh_session::EhSession};
+
+const SESSION_FILE: &str = "session.json";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let session = Arc::new(EhSession::load_or_default(SESSION_FILE)?);
+    let client = EhClient::with_session(session.clone());
+
+    let gallery_list = client.get_gallery_list("https://e-hentai.org").await?;
+    println!("{:#?}", gallery_list);
+
+    session.save(SESSION_FILE)?;
+    Ok(())
+}
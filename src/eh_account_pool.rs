@@ -0,0 +1,197 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use crate::{
+    EhResult,
+    eh_client::EhClient,
+    eh_config,
+    eh_session::EhSession,
+    eh_throttle::EhThrottle,
+    eh_url,
+    settings::{GallerySites, Settings},
+};
+
+/// Named accounts, each with its own session, uconfig and site preference.
+///
+/// All clients created by the pool share one `EhThrottle`.
+#[derive(Debug)]
+pub struct EhAccountPool {
+    account_map: RwLock<HashMap<String, Arc<EhClient>>>,
+    /// The account used for logged-out browsing.
+    anonymous_opt: RwLock<Option<String>>,
+    throttle: Arc<EhThrottle>,
+}
+
+impl Default for EhAccountPool {
+    fn default() -> Self {
+        EhAccountPool::with_throttle(Arc::new(EhThrottle::default()))
+    }
+}
+
+impl EhAccountPool {
+    pub fn new() -> EhAccountPool {
+        EhAccountPool::default()
+    }
+
+    pub fn with_throttle(throttle: Arc<EhThrottle>) -> EhAccountPool {
+        EhAccountPool {
+            account_map: RwLock::new(HashMap::new()),
+            anonymous_opt: RwLock::new(None),
+            throttle,
+        }
+    }
+
+    pub fn throttle(&self) -> &Arc<EhThrottle> {
+        &self.throttle
+    }
+
+    /// Add or replace the account `name`, setting its uconfig cookie if given.
+    ///
+    /// The name is also the file name of the session in `save_sessions`, so
+    /// it cannot be empty, `.`, `..` or contain a path separator.
+    pub fn add_account(&self, name: &str, session: Arc<EhSession>, settings: Settings, uconfig_opt: Option<&str>) -> EhResult<Arc<EhClient>> {
+        if !is_valid_name(name) {
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("invalid account name: {:?}", name)).into());
+        }
+
+        if let Some(uconfig) = uconfig_opt {
+            session.set_uconfig(uconfig);
+        }
+
+        let client = Arc::new(EhClient::with_throttle(session, settings, self.throttle.clone()));
+        self.account_map.write().unwrap().insert(String::from(name), client.clone());
+
+        Ok(client)
+    }
+
+    /// Add an account restored from a file written by `save_sessions`.
+    pub fn load_account<P: AsRef<Path>>(&self, name: &str, path: P, settings: Settings, uconfig_opt: Option<&str>) -> EhResult<Arc<EhClient>> {
+        let session = Arc::new(EhSession::load_or_default(path)?);
+        self.add_account(name, session, settings, uconfig_opt)
+    }
+
+    /// Add an account without cookies and mark it as the anonymous one,
+    /// replacing the previous mark.
+    pub fn add_anonymous(&self, name: &str, settings: Settings, uconfig_opt: Option<&str>) -> EhResult<Arc<EhClient>> {
+        let client = self.add_account(name, Arc::new(EhSession::new()), settings, uconfig_opt)?;
+        *self.anonymous_opt.write().unwrap() = Some(String::from(name));
+
+        Ok(client)
+    }
+
+    pub fn remove_account(&self, name: &str) -> Option<Arc<EhClient>> {
+        // never hold both locks, see `save_sessions`.
+        {
+            let mut anonymous_opt = self.anonymous_opt.write().unwrap();
+            if anonymous_opt.as_deref() == Some(name) {
+                *anonymous_opt = None;
+            }
+        }
+
+        self.account_map.write().unwrap().remove(name)
+    }
+
+    /// The client requests for `name` should be sent through.
+    pub fn account(&self, name: &str) -> Option<Arc<EhClient>> {
+        self.account_map.read().unwrap().get(name).cloned()
+    }
+
+    /// The uconfig cookie of `name` for the site it browses.
+    pub fn uconfig(&self, name: &str) -> Option<String> {
+        let client = self.account(name)?;
+        let domain = match client.settings().site() {
+            GallerySites::E => eh_url::DOMAIN_E,
+            _ => eh_url::DOMAIN_EX,
+        };
+
+        client.session().get_cookie(domain, eh_config::KEY_UCONFIG)
+    }
+
+    pub fn anonymous(&self) -> Option<Arc<EhClient>> {
+        let anonymous_opt = self.anonymous_opt.read().unwrap().clone();
+        anonymous_opt.and_then(|name| self.account(&name))
+    }
+
+    pub fn is_anonymous(&self, name: &str) -> bool {
+        self.anonymous_opt.read().unwrap().as_deref() == Some(name)
+    }
+
+    /// Sorted account names.
+    pub fn names(&self) -> Vec<String> {
+        let mut name_vec = self.account_map.read().unwrap()
+            .keys()
+            .cloned()
+            .collect::<Vec<String>>();
+        name_vec.sort();
+
+        name_vec
+    }
+
+    /// Write the session of every account but the anonymous one to `<dir>/<name>.json`.
+    ///
+    /// The accounts are collected first, the files are written without
+    /// holding any lock.
+    pub fn save_sessions<P: AsRef<Path>>(&self, dir: P) -> EhResult<()> {
+        let anonymous_opt = self.anonymous_opt.read().unwrap().clone();
+        let account_vec = self.account_map.read().unwrap()
+            .iter()
+            .filter(|(name, _)| anonymous_opt.as_ref() != Some(*name))
+            .map(|(name, client)| (name.clone(), client.clone()))
+            .collect::<Vec<(String, Arc<EhClient>)>>();
+
+        for (name, client) in account_vec {
+            client.session().save(dir.as_ref().join(format!("{}.json", name)))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', '\0'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_test() {
+        let pool = EhAccountPool::new();
+
+        let session = Arc::new(EhSession::new());
+        session.import_cookie_header("ipb_member_id=1; ipb_pass_hash=abc");
+        let mut settings = Settings::new();
+        settings.set_site(GallerySites::EX);
+        pool.add_account("alice", session, settings, Some("dm_t-xr_a")).unwrap();
+        pool.add_anonymous("guest", Settings::new(), None).unwrap();
+
+        assert_eq!(pool.names(), vec!["alice", "guest"]);
+        assert!(pool.account("alice").unwrap().session().is_signed_in());
+        assert_eq!(pool.account("alice").unwrap().settings().site(), GallerySites::EX);
+        assert!(!pool.anonymous().unwrap().session().is_signed_in());
+        assert!(pool.account("bob").is_none());
+        assert_eq!(pool.uconfig("alice").as_deref(), Some("dm_t-xr_a"));
+        assert_eq!(pool.account("alice").unwrap().session().get_cookie(eh_url::DOMAIN_EX, "uconfig").as_deref(), Some("dm_t-xr_a"));
+        assert_eq!(pool.uconfig("guest"), None);
+
+        pool.account("alice").unwrap().session().set_uconfig("dm_l");
+        assert_eq!(pool.uconfig("alice").as_deref(), Some("dm_l"));
+
+        pool.remove_account("guest");
+        assert!(pool.anonymous().is_none());
+    }
+
+    #[test]
+    fn invalid_name_test() {
+        let pool = EhAccountPool::new();
+        for name in ["", ".", "..", "../x", "a/b", "a\\b"] {
+            assert!(pool.add_account(name, Arc::new(EhSession::new()), Settings::new(), None).is_err());
+        }
+
+        assert!(pool.add_account("..alice", Arc::new(EhSession::new()), Settings::new(), None).is_ok());
+    }
+}
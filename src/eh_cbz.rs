@@ -0,0 +1,282 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Datelike;
+use zip::{CompressionMethod, ZipWriter, write::FileOptions};
+use crate::{
+    EhResult,
+    eh_downloader,
+    eh_url::EhUrl,
+    settings::Settings,
+    structures::{Category, GalleryDetail},
+};
+
+/// The `ComicInfo.xml` of a CBZ, the metadata format of ComicRack read by
+/// Komga, Kavita and Tachiyomi.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ComicInfo {
+    pub title: String,
+    pub series: String,
+    pub localized_series_opt: Option<String>,
+    pub writer_vec: Vec<String>,
+    pub penciller_vec: Vec<String>,
+    pub publisher: String,
+    pub genre_vec: Vec<String>,
+    pub tag_vec: Vec<String>,
+    pub character_vec: Vec<String>,
+    pub team_vec: Vec<String>,
+    pub series_group_vec: Vec<String>,
+    pub language_iso_opt: Option<String>,
+    pub page_count: u32,
+    /// From 0 to 5.
+    pub community_rating_opt: Option<f32>,
+    pub web: String,
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+/// Writes a CBZ one page at a time, the pages can come in any order.
+pub struct CbzWriter {
+    zip: ZipWriter<File>,
+}
+
+impl ComicInfo {
+    /// Artists are both the writers and the pencillers, groups the teams and
+    /// parodies the series groups. The genres are the category followed by
+    /// the female, male, mixed and other tags, the tags of the remaining
+    /// namespaces are kept as tags. Both are written as `namespace:tag`.
+    pub fn new(detail: &GalleryDetail, settings: &Settings) -> ComicInfo {
+        let mut comic_info = ComicInfo {
+            title: detail.title.clone(),
+            series: detail.parsed_title().title,
+            localized_series_opt: Some(detail.title_jpn.clone()).filter(|title_jpn| !title_jpn.is_empty()),
+            publisher: detail.uploader.clone(),
+            genre_vec: vec![Category::from_value(detail.category).string],
+            language_iso_opt: detail.language_detection()
+                .and_then(|detection| detection.language.iso_code_opt())
+                .map(String::from),
+            page_count: detail.detail.pages,
+            community_rating_opt: detail.rating_opt,
+            web: EhUrl::new(settings.clone()).gallery_detail(detail.identity.gid, &detail.identity.token, false, None),
+            year: detail.detail.posted.year(),
+            month: detail.detail.posted.month(),
+            day: detail.detail.posted.day(),
+            ..ComicInfo::default()
+        };
+
+        for tag_group in &detail.tag_group_vec {
+            let tag_vec = tag_group.tag_vec.clone();
+            match tag_group.tag_group_name.as_str() {
+                NAMESPACE_ARTIST => {
+                    comic_info.writer_vec.extend(tag_vec.clone());
+                    comic_info.penciller_vec.extend(tag_vec);
+                }
+                NAMESPACE_GROUP => comic_info.team_vec.extend(tag_vec),
+                NAMESPACE_CHARACTER => comic_info.character_vec.extend(tag_vec),
+                NAMESPACE_PARODY => comic_info.series_group_vec.extend(tag_vec),
+                NAMESPACE_LANGUAGE => {}
+                namespace if GENRE_NAMESPACES.contains(&namespace) => comic_info.genre_vec.extend(
+                    tag_vec.into_iter().map(|tag| format!("{}:{}", namespace, tag))
+                ),
+                namespace => comic_info.tag_vec.extend(
+                    tag_vec.into_iter().map(|tag| format!("{}:{}", namespace, tag))
+                ),
+            }
+        }
+
+        comic_info
+    }
+
+    /// Elements are in the sequence order of `ComicInfo.xsd`, which strict
+    /// readers check. `LocalizedSeries`, added in v2.1, comes last.
+    pub fn to_xml(&self) -> String {
+        let mut element_vec = vec![
+            element("Title", &self.title),
+            element("Series", &self.series),
+            element("Year", &self.year.to_string()),
+            element("Month", &self.month.to_string()),
+            element("Day", &self.day.to_string()),
+        ];
+
+        let push_list = |element_vec: &mut Vec<String>, name: &str, value_vec: &[String]| {
+            if !value_vec.is_empty() {
+                element_vec.push(element(name, &value_vec.join(", ")));
+            }
+        };
+
+        push_list(&mut element_vec, "Writer", &self.writer_vec);
+        push_list(&mut element_vec, "Penciller", &self.penciller_vec);
+        element_vec.push(element("Publisher", &self.publisher));
+        push_list(&mut element_vec, "Genre", &self.genre_vec);
+        push_list(&mut element_vec, "Tags", &self.tag_vec);
+        element_vec.push(element("Web", &self.web));
+        element_vec.push(element("PageCount", &self.page_count.to_string()));
+
+        if let Some(language_iso) = &self.language_iso_opt {
+            element_vec.push(element("LanguageISO", language_iso));
+        }
+
+        push_list(&mut element_vec, "Characters", &self.character_vec);
+        push_list(&mut element_vec, "Teams", &self.team_vec);
+        push_list(&mut element_vec, "SeriesGroup", &self.series_group_vec);
+
+        if let Some(community_rating) = self.community_rating_opt {
+            element_vec.push(element("CommunityRating", &format!("{:.1}", community_rating)));
+        }
+
+        if let Some(localized_series) = &self.localized_series_opt {
+            element_vec.push(element("LocalizedSeries", localized_series));
+        }
+
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<ComicInfo xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\" xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\">\n{}\n</ComicInfo>\n",
+            element_vec.join("\n"),
+        )
+    }
+}
+
+impl CbzWriter {
+    /// Create the archive at `path`, starting with `ComicInfo.xml`.
+    pub fn create<P: AsRef<Path>>(path: P, comic_info: &ComicInfo) -> EhResult<CbzWriter> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        zip.start_file(COMIC_INFO_FILE, FileOptions::default()).map_err(std::io::Error::from)?;
+        zip.write_all(comic_info.to_xml().as_bytes())?;
+
+        Ok(CbzWriter { zip })
+    }
+
+    /// Add an image as is, images are not worth compressing again.
+    pub fn add_page(&mut self, filename: &str, bytes: &[u8]) -> EhResult<()> {
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        self.zip.start_file(filename, options).map_err(std::io::Error::from)?;
+        self.zip.write_all(bytes)?;
+
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> EhResult<()> {
+        self.zip.finish().map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
+
+/// Pack a folder written by the `Downloader` into a CBZ at `path`.
+///
+/// Fails without writing anything if the folder has no page, and removes
+/// the file if a page cannot be read.
+pub fn export_cbz<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, comic_info: &ComicInfo, path: Q) -> EhResult<()> {
+    let page_path_vec = eh_downloader::downloaded_page_vec(dir)?;
+    if page_path_vec.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no page to export").into());
+    }
+
+    let path = path.as_ref();
+    let result = write_cbz(&page_path_vec, comic_info, path);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+fn write_cbz(page_path_vec: &[PathBuf], comic_info: &ComicInfo, path: &Path) -> EhResult<()> {
+    let mut writer = CbzWriter::create(path, comic_info)?;
+    for page_path in page_path_vec {
+        let Some(filename) = page_path.file_name().and_then(|filename| filename.to_str()) else {
+            continue;
+        };
+
+        writer.add_page(filename, &fs::read(page_path)?)?;
+    }
+
+    writer.finish()
+}
+
+fn element(name: &str, value: &str) -> String {
+    format!("  <{}>{}</{}>", name, escape_xml(value), name)
+}
+
+pub(crate) fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+const COMIC_INFO_FILE: &str = "ComicInfo.xml";
+
+const NAMESPACE_ARTIST: &str = "artist";
+const NAMESPACE_GROUP: &str = "group";
+const NAMESPACE_CHARACTER: &str = "character";
+const NAMESPACE_PARODY: &str = "parody";
+const NAMESPACE_LANGUAGE: &str = "language";
+const GENRE_NAMESPACES: [&str; 4] = ["female", "male", "mixed", "other"];
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn to_xml_test() {
+        let comic_info = ComicInfo {
+            title: String::from("[Circle (Artist)] Title & More"),
+            series: String::from("Title & More"),
+            writer_vec: vec![String::from("artist a"), String::from("artist b")],
+            genre_vec: vec![String::from("Doujinshi"), String::from("female:glasses")],
+            character_vec: vec![String::from("character a")],
+            community_rating_opt: Some(4.5),
+            language_iso_opt: Some(String::from("en")),
+            localized_series_opt: Some(String::from("タイトル")),
+            ..ComicInfo::default()
+        };
+
+        let xml = comic_info.to_xml();
+        let position = |name: &str| xml.find(&format!("<{}>", name)).unwrap();
+        let name_vec = ["Title", "Series", "Year", "Writer", "Publisher", "Genre", "Web", "PageCount", "LanguageISO", "Characters", "CommunityRating", "LocalizedSeries"];
+        assert!(name_vec.windows(2).all(|w| position(w[0]) < position(w[1])));
+        assert!(xml.contains("<Genre>Doujinshi, female:glasses</Genre>"));
+        assert!(xml.contains("<Title>[Circle (Artist)] Title &amp; More</Title>"));
+        assert!(xml.contains("<Writer>artist a, artist b</Writer>"));
+        assert!(xml.contains("<CommunityRating>4.5</CommunityRating>"));
+        assert!(xml.contains("<LanguageISO>en</LanguageISO>"));
+        assert!(!xml.contains("<Penciller>"));
+    }
+
+    #[test]
+    fn export_cbz_test() {
+        let dir = std::env::temp_dir().join(format!("eh_cbz_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("002.png"), b"2").unwrap();
+        fs::write(dir.join("001.jpg"), b"1").unwrap();
+        fs::write(dir.join("manifest.json"), b"{}").unwrap();
+
+        let path = dir.join("gallery.cbz");
+        export_cbz(&dir, &ComicInfo::default(), &path).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        let name_vec = archive.file_names().map(String::from).collect::<Vec<String>>();
+        assert_eq!(name_vec.len(), 3);
+        assert!(name_vec.contains(&String::from(COMIC_INFO_FILE)));
+
+        let mut s = String::new();
+        archive.by_name("002.png").unwrap().read_to_string(&mut s).unwrap();
+        assert_eq!(s, "2");
+
+        let empty_dir = dir.join("empty");
+        fs::create_dir_all(&empty_dir).unwrap();
+        let path = dir.join("empty.cbz");
+        assert!(export_cbz(&empty_dir, &ComicInfo::default(), &path).is_err());
+        assert!(!path.exists());
+
+        // a folder named as a page cannot be read.
+        fs::create_dir_all(empty_dir.join("001.jpg")).unwrap();
+        assert!(export_cbz(&empty_dir, &ComicInfo::default(), &path).is_err());
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
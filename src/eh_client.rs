@@ -1,10 +1,24 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use futures::{StreamExt, TryStreamExt, stream};
 use reqwest::header;
 use reqwest::header::HeaderMap;
 use crate::{
+    EhError,
     EhResult,
+    eh_config,
     settings::Settings,
+    eh_session::{self, EhSession},
+    eh_client_builder::EhClientBuilder,
+    eh_retry::{RetryCondition, RetryPolicy},
+    eh_similar::{self, SimilarGallery, SimilarityMode},
+    eh_throttle::EhThrottle,
     eh_url::{self, EhUrl},
-    structures::{GalleryList, SignIn, GalleryDetail},
+    parser::{self, ParseError},
+    structures::{Forums, GalleryList, IpBan, Profile, SignIn, SignInError, GalleryDetail, GalleryIdentity,
+                 GalleryMetadata, GalleryMetadataList, GalleryMultiPageViewer, GalleryPage, GalleryPageApi, GalleryPageUrl, GalleryPreviewSet,
+                 GalleryUnavailable, GalleryVersion, ImageLimits, MultiPageViewerDispatch},
 };
 
 #[derive(Debug)]
@@ -12,26 +26,250 @@ pub struct EhClient {
     client: reqwest::Client,
     eh_url: EhUrl,
     settings: Settings,
+    session: Arc<EhSession>,
+    throttle: Arc<EhThrottle>,
+    retry_policy: RetryPolicy,
+    page_url_cache: Mutex<HashMap<GalleryIdentity, Vec<GalleryPageUrl>>>,
+    /// The show key of the `showpage` API, by gid.
+    show_key_cache: Mutex<HashMap<u64, String>>,
+}
+
+impl Default for EhClient {
+    fn default() -> Self {
+        EhClient::new()
+    }
 }
 
 impl EhClient {
     pub fn new() -> EhClient {
-        let client = reqwest::Client::builder()
-            .cookie_store(true)
-            .referer(true)
+        EhClient::with_session(Arc::new(EhSession::new()))
+    }
+
+    pub fn builder() -> EhClientBuilder {
+        EhClientBuilder::new()
+    }
+
+    /// Every cookie the client receives is stored in `session`,
+    /// so it can be saved and restored across restarts.
+    pub fn with_session(session: Arc<EhSession>) -> EhClient {
+        EhClient::with_settings(session, Settings::new())
+    }
+
+    pub fn with_settings(session: Arc<EhSession>, settings: Settings) -> EhClient {
+        EhClient::with_throttle(session, settings, Arc::new(EhThrottle::default()))
+    }
+
+    /// Clients sending from the same address should share `throttle`.
+    pub fn with_throttle(session: Arc<EhSession>, settings: Settings, throttle: Arc<EhThrottle>) -> EhClient {
+        EhClient::builder()
+            .session(session)
+            .settings(settings)
+            .throttle(throttle)
             .build()
-            .unwrap();
+            .unwrap()
+    }
 
-        let settings = Settings::new();
+    pub(crate) fn from_parts(
+        client: reqwest::Client,
+        settings: Settings,
+        session: Arc<EhSession>,
+        throttle: Arc<EhThrottle>,
+        retry_policy: RetryPolicy,
+    ) -> EhClient {
         let eh_url = EhUrl::new(settings.clone());
+        EhClient {
+            client,
+            eh_url,
+            settings,
+            session,
+            throttle,
+            retry_policy,
+            page_url_cache: Mutex::new(HashMap::new()),
+            show_key_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn session(&self) -> &Arc<EhSession> {
+        &self.session
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) {
+        self.retry_policy = retry_policy;
+    }
+
+    /// Send the requests of the site to `host` instead, such as `http://127.0.0.1:8080/`.
+    #[cfg(test)]
+    pub(crate) fn set_host(&mut self, host: &str) {
+        self.eh_url = EhUrl::with_host(self.settings.clone(), host);
+    }
+
+    /// Probe whether the signed-in account can browse exhentai.org.
+    ///
+    /// A stale `igneous=mystery` is dropped first, so that exhentai.org
+    /// negotiates a fresh one. Call it before switching to `GallerySites::EX`.
+    pub async fn check_exhentai_access(&self) -> EhResult<bool> {
+        if !self.session.is_signed_in() {
+            return Ok(false);
+        }
+
+        self.session.copy_member_cookies_to_exhentai();
+        if self.session.get_cookie(eh_url::DOMAIN_EX, eh_session::KEY_IGNEOUS).as_deref() == Some(eh_session::IGNEOUS_MYSTERY) {
+            self.session.remove_cookie(eh_url::DOMAIN_EX, eh_session::KEY_IGNEOUS);
+        }
+
+        let request = self.client.get(eh_url::HOST_EX);
+        let res = self.send_text(Method::MethodGetGalleryList, request).await?;
+        Ok(check_exhentai_response(&self.session, &res.content_type, &res.body).is_ok())
+    }
+
+    /// Load a page of the selected site.
+    ///
+    /// On exhentai.org an empty body or the sad panda image is turned into
+    /// `EhError::ExhentaiAccessDenied` instead of being handed to a parser.
+    /// The host of `url` decides, some pages such as home.php are always on e-hentai.org.
+    async fn get_html(&self, method: Method, url: &str) -> EhResult<String> {
+        let is_exhentai = is_exhentai_url(url);
+        if is_exhentai {
+            self.session.copy_member_cookies_to_exhentai();
+        }
+
+        let headers = HeaderMap::from_iter([
+            (header::REFERER, self.eh_url.referer().parse().unwrap()),
+        ]);
+
+        let res = self.send_text(method, self.client.get(url).headers(headers)).await?;
+        if is_exhentai {
+            check_exhentai_response(&self.session, &res.content_type, &res.body)?;
+        }
+
+        Ok(res.body)
+    }
+
+    async fn post_api(&self, method: Method, json: &serde_json::Value) -> EhResult<String> {
+        let headers = HeaderMap::from_iter([
+            (header::REFERER, self.eh_url.referer().parse().unwrap()),
+            (header::ORIGIN, self.eh_url.origin().parse().unwrap()),
+        ]);
+
+        let request = self.client.post(self.eh_url.api()).headers(headers).json(json);
+        let res = self.send_text(method, request).await?;
+        Ok(res.body)
+    }
+
+    /// Every request goes through here: it waits for the throttle, and turns
+    /// the temporary ban page into `EhError::IpBanned`, which the throttle
+    /// remembers for all clients sharing it.
+    ///
+    /// Failures are retried as the `RetryPolicy` rule of `method` allows. Once
+    /// the attempts are used up, a 5xx or truncated response is returned as is.
+    async fn send_text(&self, method: Method, request: reqwest::RequestBuilder) -> EhResult<TextResponse> {
+        let rule = self.retry_policy.rule(method);
+        let request = request.build()?;
 
-        EhClient { client, settings, eh_url }
+        let mut attempt = 1;
+        loop {
+            // a form body is always cloneable.
+            let req = request.try_clone().unwrap();
+            self.throttle.acquire(req.url().as_str()).await?;
+
+            let result = self.execute_text(req).await;
+            let condition_opt = match &result {
+                Ok(res) => {
+                    if let Ok(ban) = res.body.parse::<IpBan>() {
+                        self.throttle.set_banned(ban.expires_in);
+                        return Err(EhError::IpBanned { expires_in: ban.expires_in });
+                    }
+
+                    retry_condition_of(res)
+                }
+                Err(e) => retry_condition_of_error(e),
+            };
+
+            match condition_opt {
+                Some(condition) if rule.should_retry(attempt, condition) => {
+                    tokio::time::sleep(rule.delay(attempt)).await;
+                    attempt += 1;
+                }
+                _ => return Ok(result?),
+            }
+        }
     }
 
+    /// `send_text` for images: retried by the same policy, and a ban page or
+    /// the 509 image served in place of the image fails at once.
+    async fn send_bytes(&self, method: Method, request: reqwest::RequestBuilder) -> EhResult<BytesResponse> {
+        let rule = self.retry_policy.rule(method);
+        let request = request.build()?;
+
+        let mut attempt = 1;
+        loop {
+            // a GET has no body to consume.
+            let req = request.try_clone().unwrap();
+            self.throttle.acquire(req.url().as_str()).await?;
+
+            let result = self.execute_bytes(req).await;
+            let condition_opt = match &result {
+                Ok(res) => {
+                    if res.status.as_u16() == STATUS_QUOTA_EXCEEDED || parser::is_quota_exceeded_image(res.url.as_str()) {
+                        return Err(EhError::ImageQuotaExceeded);
+                    }
+
+                    if res.content_type.starts_with("text/") {
+                        if let Ok(ban) = String::from_utf8_lossy(&res.body).parse::<IpBan>() {
+                            self.throttle.set_banned(ban.expires_in);
+                            return Err(EhError::IpBanned { expires_in: ban.expires_in });
+                        }
+                    }
+
+                    res.status.is_server_error().then_some(RetryCondition::ServerError)
+                }
+                Err(e) => retry_condition_of_error(e),
+            };
+
+            match condition_opt {
+                Some(condition) if rule.should_retry(attempt, condition) => {
+                    tokio::time::sleep(rule.delay(attempt)).await;
+                    attempt += 1;
+                }
+                _ => return Ok(result?),
+            }
+        }
+    }
+
+    async fn execute_bytes(&self, request: reqwest::Request) -> reqwest::Result<BytesResponse> {
+        let res = self.client.execute(request).await?;
+        let status = res.status();
+        let content_type = content_type_of(&res);
+        let url = res.url().clone();
+        let body = res.bytes().await?.to_vec();
+
+        Ok(BytesResponse { status, content_type, url, body })
+    }
+
+    async fn execute_text(&self, request: reqwest::Request) -> reqwest::Result<TextResponse> {
+        let res = self.client.execute(request).await?;
+        let status = res.status();
+        let content_type = content_type_of(&res);
+        let body = res.text().await?;
+
+        Ok(TextResponse { status, content_type, body })
+    }
+
+    /// Sign in through the forums login form.
+    ///
+    /// The forums index is loaded afterwards, to confirm the session
+    /// and to learn the member id.
     pub async fn sign_in(&self, username: &str, password: &str) -> EhResult<SignIn> {
         let referer = "https://forums.e-hentai.org/index.php?act=Login&CODE=0";
         let origin = "https://forums.e-hentai.org";
-        ;
 
         let params = [
             ("referer", referer),
@@ -42,57 +280,400 @@ impl EhClient {
             ("CookieDate", "1"),
         ];
 
-        let mut headers = HeaderMap::from_iter([
+        let headers = HeaderMap::from_iter([
             (header::REFERER, referer.parse().unwrap()),
             (header::ORIGIN, origin.parse().unwrap())
         ]);
 
-        let res = self.client
+        let request = self.client
             .post(eh_url::API_SIGN_IN)
             .headers(headers)
-            .form(&params)
-            .send()
-            .await?
-            .text()
-            .await?;
+            .form(&params);
+
+        let res = self.send_text(Method::MethodSignIn, request).await?;
+        if res.status.is_server_error() {
+            return Err(EhError::SignInFailed(SignInError::ForumDown));
+        }
 
-        Ok(res.parse::<SignIn>()?)
+        // the success page can have a postcolor span or a reCAPTCHA too.
+        let res = res.body;
+        let mut sign_in = match res.parse::<SignIn>() {
+            Ok(sign_in) => sign_in,
+            Err(e) => return Err(match res.parse::<SignInError>() {
+                Ok(error) => EhError::SignInFailed(error),
+                Err(_) => e.into(),
+            }),
+        };
+        let forums = self.verify_session().await?;
+        sign_in.member_id_opt = Some(forums.member_id);
+
+        Ok(sign_in)
     }
 
-    pub async fn get_gallery_list(&self, url: &str) -> EhResult<GalleryList> {
-        let headers = HeaderMap::from_iter([
-            (header::REFERER, self.eh_url.referer().parse().unwrap()),
-        ]);
+    /// The signed-in member's forum profile.
+    pub async fn get_profile(&self) -> EhResult<Profile> {
+        let forums = self.get_forums(Method::MethodGetProfile).await?;
 
-        let res = self.client
-            .get(url)
-            .headers(headers)
-            .send()
-            .await?
-            .text()
-            .await?;
+        let res = self.get_text(Method::MethodGetProfile, &forums.user_link).await?;
+        let mut profile = res.parse::<Profile>()?;
+        profile.member_id_opt = Some(forums.member_id);
 
+        Ok(profile)
+    }
+
+    /// The forums index only shows `#userlinks` to a signed-in member.
+    async fn get_forums(&self, method: Method) -> EhResult<Forums> {
+        let res = self.get_text(method, eh_url::URL_FORUMS).await?;
+        Ok(res.parse::<Forums>().map_err(|_| ParseError::SignInRequired)?)
+    }
+
+    /// The forums member has to be the one the session cookies belong to.
+    async fn verify_session(&self) -> EhResult<Forums> {
+        let forums = self.get_forums(Method::MethodSignIn)
+            .await
+            .map_err(|_| EhError::SignInFailed(SignInError::SessionNotEstablished))?;
+
+        match self.session.member_id_opt() {
+            Some(member_id) if member_id == forums.member_id => Ok(forums),
+            _ => Err(EhError::SignInFailed(SignInError::SessionNotEstablished)),
+        }
+    }
+
+    async fn get_text(&self, method: Method, url: &str) -> EhResult<String> {
+        let res = self.send_text(method, self.client.get(url)).await?;
+        Ok(res.body)
+    }
+
+    pub async fn get_gallery_list(&self, url: &str) -> EhResult<GalleryList> {
+        let res = self.get_html(Method::MethodGetGalleryList, url).await?;
         Ok(res.parse::<GalleryList>()?)
     }
 
+    /// Galleries behind the content warning are reloaded with `nw`,
+    /// unless the settings ask to show the warning.
     pub async fn get_gallery_detail(&self, url: &str) -> EhResult<GalleryDetail> {
-        let headers = HeaderMap::from_iter([
-            (header::REFERER, self.eh_url.referer().parse().unwrap()),
-        ]);
+        self.get_gallery_page(Method::MethodGetGalleryDetail, url).await
+    }
 
-        let res = self.client
-            .get(url)
-            .headers(headers)
-            .send()
-            .await?
-            .text()
+    /// Load the previews of the `page`th preview page, from 0.
+    pub async fn get_preview_set(&self, identity: &GalleryIdentity, page: u32) -> EhResult<GalleryPreviewSet> {
+        let url = self.eh_url.gallery_detail(identity.gid, &identity.token, false, Some(page));
+        self.get_gallery_page(Method::MethodGetPreviewSet, &url).await
+    }
+
+    /// Every page url of a gallery, ordered by page.
+    ///
+    /// The preview pages are loaded a few at a time, as fast as the throttle allows.
+    /// The result is cached for the lifetime of the client.
+    pub async fn all_page_urls(&self, identity: &GalleryIdentity) -> EhResult<Vec<GalleryPageUrl>> {
+        if let Some(page_url_vec) = self.page_url_cache.lock().unwrap().get(identity) {
+            return Ok(page_url_vec.clone());
+        }
+
+        let url = self.eh_url.gallery_detail(identity.gid, &identity.token, false, None);
+        let detail = self.get_gallery_detail(&url).await?;
+        let mut page_url_vec = detail.preview_set.page_url_vec()?;
+
+        let preview_set_vec = stream::iter(1..detail.preview_pages)
+            .map(|page| self.get_preview_set(identity, page))
+            .buffered(PREVIEW_SET_CONCURRENCY)
+            .try_collect::<Vec<GalleryPreviewSet>>()
             .await?;
 
-        Ok(res.parse::<GalleryDetail>()?)
+        for preview_set in preview_set_vec {
+            page_url_vec.extend(preview_set.page_url_vec()?);
+        }
+
+        page_url_vec.sort_by_key(|page_url| page_url.page);
+        page_url_vec.dedup_by_key(|page_url| page_url.page);
+
+        self.page_url_cache.lock().unwrap().insert(identity.clone(), page_url_vec.clone());
+        Ok(page_url_vec)
+    }
+
+    /// Load an image page.
+    ///
+    /// The first page of a gallery is loaded as html, which yields the show key.
+    /// Later pages go through the `showpage` API with that key, falling back
+    /// to the html page if the API refuses it.
+    pub async fn get_page(&self, page_url: &GalleryPageUrl) -> EhResult<GalleryPage> {
+        let show_key_opt = self.show_key_cache.lock().unwrap().get(&page_url.gid).cloned();
+        if let Some(show_key) = show_key_opt {
+            match self.get_page_api(page_url, &show_key).await {
+                Ok(page_api) => {
+                    return Ok(GalleryPage {
+                        image_url: page_api.image_url,
+                        skip_hath_key: page_api.skip_hath_key,
+                        origin_image_url_opt: page_api.origin_image_url_opt,
+                        show_key,
+                        filename: page_api.filename,
+                        image_size: page_api.image_size,
+                        origin_image_size_opt: page_api.origin_image_size_opt,
+                        navigation_opt: page_api.navigation_opt,
+                    });
+                }
+                Err(EhError::ParseError(_) | EhError::FromServerError(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        let url = self.eh_url.page(page_url.gid, page_url.page, &page_url.p_token);
+        self.get_page_html(page_url.gid, &url).await
+    }
+
+    /// Load an image page again from another H@H node, when the image of
+    /// `page` cannot be downloaded.
+    pub async fn reload_page(&self, page_url: &GalleryPageUrl, page: &GalleryPage) -> EhResult<GalleryPage> {
+        let url = self.eh_url.page(page_url.gid, page_url.page, &page_url.p_token);
+        let url = append_query(&url, KEY_SKIP_HATH, &page.skip_hath_key)?;
+        self.get_page_html(page_url.gid, &url).await
+    }
+
+    async fn get_page_html(&self, gid: u64, url: &str) -> EhResult<GalleryPage> {
+        let res = self.get_html(Method::MethodGetGalleryPage, url).await?;
+        let page = res.parse::<GalleryPage>()?;
+        self.show_key_cache.lock().unwrap().insert(gid, page.show_key.clone());
+        Ok(page)
+    }
+
+    async fn get_page_api(&self, page_url: &GalleryPageUrl, show_key: &str) -> EhResult<GalleryPageApi> {
+        let json = serde_json::json!({
+            "method": "showpage",
+            "gid": page_url.gid,
+            "page": page_url.page + 1,
+            "imgkey": page_url.p_token,
+            "showkey": show_key,
+        });
+
+        let res = self.post_api(Method::MethodGetGalleryPageApi, &json).await?;
+        Ok(res.parse::<GalleryPageApi>()?)
+    }
+
+    /// Load the Multi-Page Viewer of a gallery, which lists every page url
+    /// in one request. Requires an account with the Multi-Page Viewer perk.
+    pub async fn get_mpv(&self, identity: &GalleryIdentity) -> EhResult<GalleryMultiPageViewer> {
+        let url = self.eh_url.gallery_multi_page_viewer(identity.gid, &identity.token);
+        self.get_gallery_page(Method::MethodGetMultiPageViewer, &url).await
+    }
+
+    /// Resolve the image of the `page`th page of `mpv`, from 0.
+    pub async fn mpv_dispatch(&self, mpv: &GalleryMultiPageViewer, page: u32) -> EhResult<MultiPageViewerDispatch> {
+        let image = mpv.image_vec.get(page as usize).ok_or(ParseError::OutOfRange)?;
+        let json = serde_json::json!({
+            "method": "imagedispatch",
+            "gid": mpv.gid,
+            "page": page + 1,
+            "imgkey": image.p_token,
+            "mpvkey": mpv.mpv_key,
+        });
+
+        let res = self.post_api(Method::MethodMultiPageViewerDispatch, &json).await?;
+        let mut dispatch = res.parse::<MultiPageViewerDispatch>()?;
+        dispatch.origin_image_url_opt = dispatch.origin_image_url_opt
+            .map(|url| self.resolve_url(&url))
+            .transpose()?;
+
+        Ok(dispatch)
+    }
+
+    /// Download an image, as found in `GalleryPage::image_url`.
+    ///
+    /// Transient failures are retried by the rule of `Method::MethodGetImage`.
+    /// Fails with `EhError::ImageQuotaExceeded` if the 509 image is served instead.
+    pub async fn get_image(&self, url: &str) -> EhResult<Vec<u8>> {
+        let res = self.send_bytes(Method::MethodGetImage, self.client.get(url)).await?;
+        if !res.status.is_success() {
+            let error = format!("image server answered {}", res.status);
+            return Err(EhError::FromServerError(ParseError::FromServer(error)));
+        }
+
+        Ok(res.body)
+    }
+
+    /// The image quota of the signed in account.
+    pub async fn get_image_limits(&self) -> EhResult<ImageLimits> {
+        let res = self.get_html(Method::MethodGetImageLimits, eh_url::URL_HOME).await?;
+        Ok(res.parse::<ImageLimits>()?)
+    }
+
+    /// `url` relative to the site, as the API returns some of them.
+    fn resolve_url(&self, url: &str) -> EhResult<String> {
+        let base = reqwest::Url::parse(&self.eh_url.referer()).map_err(|e| ParseError::Other(Box::new(e)))?;
+        let url = base.join(url).map_err(|e| ParseError::Other(Box::new(e)))?;
+        Ok(String::from(url))
+    }
+
+    async fn get_gallery_page<T: FromStr<Err=ParseError>>(&self, method: Method, url: &str) -> EhResult<T> {
+        let res = self.get_html(method, url).await?;
+        let result = res.parse::<T>();
+
+        match (result, self.settings.content_warning().value_opt()) {
+            (Err(ParseError::ContentWarning | ParseError::GalleryPining), Some(nw)) => {
+                let url = append_query(url, eh_config::KEY_CONTENT_WARNING, nw)?;
+                let res = self.get_html(method, &url).await?;
+                Ok(res.parse::<T>()?)
+            }
+            (result, _) => Ok(result?),
+        }
+    }
+
+    /// Search the galleries similar to `source` by `mode`, closest first.
+    ///
+    /// Only the first page of results is ranked, see `GalleryList::rank_similar`.
+    pub async fn find_similar(&self, source: &GalleryDetail, mode: SimilarityMode) -> EhResult<Vec<SimilarGallery>> {
+        let Some(query) = eh_similar::similar_query(source, mode) else {
+            return Ok(vec![]);
+        };
+
+        let gallery_list = self.get_gallery_list(&self.eh_url.search(&query)).await?;
+        Ok(gallery_list.rank_similar(source))
+    }
+
+    /// Build the whole lineage of a gallery, oldest first.
+    ///
+    /// Parents are walked up to the first upload, every version met on the
+    /// way contributes its newer versions. An unavailable parent ends the walk.
+    pub async fn get_version_chain(&self, identity: &GalleryIdentity) -> EhResult<Vec<GalleryVersion>> {
+        let url = self.eh_url.gallery_detail(identity.gid, &identity.token, false, None);
+        let mut detail = self.get_gallery_detail(&url).await?;
+        let mut version_map = HashMap::new();
+
+        loop {
+            version_map.insert(detail.identity.gid, detail.version()?);
+            for version in &detail.newer_version_vec {
+                version_map.entry(version.identity.gid).or_insert_with(|| version.clone());
+            }
+
+            let Some(parent) = &detail.detail.parent_opt else {
+                break;
+            };
+
+            // a parent loop should not happen, stop anyway.
+            if version_map.contains_key(&parent.gid) {
+                break;
+            }
+
+            let url = self.eh_url.gallery_detail(parent.gid, &parent.token, false, None);
+            detail = match self.get_gallery_detail(&url).await {
+                Ok(detail) => detail,
+                Err(EhError::GalleryNotAvailable(_)) => break,
+                Err(e) => return Err(e),
+            };
+        }
+
+        let mut version_vec = version_map.into_values().collect::<Vec<GalleryVersion>>();
+        version_vec.sort_by_key(|version| (version.posted, version.identity.gid));
+        Ok(version_vec)
+    }
+
+    /// Load the metadata of up to 25 galleries through the gdata API,
+    /// each one comes back with the reason if it cannot be viewed.
+    pub async fn get_gallery_metadata(&self, identity_vec: &[GalleryIdentity]) -> EhResult<Vec<Result<GalleryMetadata, GalleryUnavailable>>> {
+        let gid_list = identity_vec.iter()
+            .map(|identity| serde_json::json!([identity.gid, identity.token]))
+            .collect::<Vec<serde_json::Value>>();
+
+        let json = serde_json::json!({
+            "method": "gdata",
+            "gidlist": gid_list,
+            "namespace": 1,
+        });
+
+        let res = self.post_api(Method::MethodGetGalleryMetadata, &json).await?;
+        let list = res.parse::<GalleryMetadataList>()?;
+
+        let result_vec = list.metadata_vec.into_iter()
+            .map(|metadata| match metadata.unavailable_opt() {
+                Some(kind) => Err(kind),
+                None => Ok(metadata),
+            })
+            .collect();
+
+        Ok(result_vec)
+    }
+}
+
+#[derive(Debug)]
+struct TextResponse {
+    status: reqwest::StatusCode,
+    content_type: String,
+    body: String,
+}
+
+#[derive(Debug)]
+struct BytesResponse {
+    status: reqwest::StatusCode,
+    content_type: String,
+    /// After redirects, the 509 image is a redirect.
+    url: reqwest::Url,
+    body: Vec<u8>,
+}
+
+fn append_query(url: &str, key: &str, value: &str) -> EhResult<String> {
+    let mut url = reqwest::Url::parse(url).map_err(|e| ParseError::Other(Box::new(e)))?;
+    url.query_pairs_mut().append_pair(key, value);
+    Ok(String::from(url))
+}
+
+fn content_type_of(res: &reqwest::Response) -> String {
+    res.headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+fn retry_condition_of(res: &TextResponse) -> Option<RetryCondition> {
+    if res.status.is_server_error() {
+        Some(RetryCondition::ServerError)
+    // an empty page is final, it is how exhentai.org turns accounts away.
+    } else if res.content_type.starts_with("text/html")
+        && !res.body.trim().is_empty()
+        && !res.body.trim_end().ends_with("</html>") {
+        Some(RetryCondition::TruncatedHtml)
+    } else {
+        None
+    }
+}
+
+fn retry_condition_of_error(e: &reqwest::Error) -> Option<RetryCondition> {
+    if e.is_timeout() {
+        Some(RetryCondition::Timeout)
+    } else if e.is_connect() || e.is_body() {
+        Some(RetryCondition::Connect)
+    } else {
+        None
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+fn is_exhentai_url(url: &str) -> bool {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(|host| host == eh_url::DOMAIN_EX || host.ends_with(&format!(".{}", eh_url::DOMAIN_EX))))
+        .unwrap_or(false)
+}
+
+/// Without access exhentai.org answers with an empty page, or with the sad panda
+/// gif, and leaves `igneous=mystery` behind.
+fn check_exhentai_response(session: &EhSession, content_type: &str, body: &str) -> EhResult<()> {
+    let igneous_opt = session.get_cookie(eh_url::DOMAIN_EX, eh_session::KEY_IGNEOUS);
+    if body.trim().is_empty()
+        || content_type.starts_with("image/")
+        || body.starts_with(GIF_MAGIC)
+        || igneous_opt.as_deref() == Some(eh_session::IGNEOUS_MYSTERY) {
+        return Err(EhError::ExhentaiAccessDenied);
+    }
+
+    Ok(())
+}
+
+/// Asks for the image from another H@H node.
+const KEY_SKIP_HATH: &str = "nl";
+const GIF_MAGIC: &str = "GIF8";
+const STATUS_QUOTA_EXCEEDED: u16 = 509;
+const PREVIEW_SET_CONCURRENCY: usize = 4;
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum Method {
     MethodSignIn,
     MethodGetGalleryList,
@@ -116,12 +697,70 @@ pub enum Method {
     //Added by EHentaiAPI.
     MethodGetGalleryPageApi,
     MethodGetGalleryPage,
+    MethodGetGalleryMetadata,
+    MethodGetMultiPageViewer,
+    MethodMultiPageViewerDispatch,
+    MethodGetImageLimits,
+    MethodGetImage,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn check_exhentai_response_test() {
+        let session = EhSession::new();
+        assert!(check_exhentai_response(&session, "text/html; charset=UTF-8", "").is_err());
+        assert!(check_exhentai_response(&session, "image/gif", "GIF89a").is_err());
+        assert!(check_exhentai_response(&session, "text/html; charset=UTF-8", "<html></html>").is_ok());
+
+        session.set_cookie(eh_url::DOMAIN_EX, eh_session::KEY_IGNEOUS, eh_session::IGNEOUS_MYSTERY);
+        assert!(check_exhentai_response(&session, "text/html; charset=UTF-8", "<html></html>").is_err());
+    }
+
+    #[test]
+    fn append_query_test() {
+        let url = append_query("https://e-hentai.org/g/2062067/588c82702b/", "nw", "1").unwrap();
+        assert_eq!(url, "https://e-hentai.org/g/2062067/588c82702b/?nw=1");
+
+        let url = append_query("https://e-hentai.org/g/2062067/588c82702b/?p=1", "nw", "always").unwrap();
+        assert_eq!(url, "https://e-hentai.org/g/2062067/588c82702b/?p=1&nw=always");
+    }
+
+    #[test]
+    fn is_exhentai_url_test() {
+        assert!(is_exhentai_url("https://exhentai.org/g/1/abc/"));
+        assert!(!is_exhentai_url(eh_url::URL_HOME));
+        assert!(!is_exhentai_url("https://e-hentai.org/?f_search=exhentai.org"));
+    }
+
+    #[test]
+    fn retry_condition_of_test() {
+        let res = TextResponse {
+            status: reqwest::StatusCode::OK,
+            content_type: String::from("text/html; charset=UTF-8"),
+            body: String::from("<html><body>"),
+        };
+        assert_eq!(retry_condition_of(&res), Some(RetryCondition::TruncatedHtml));
+
+        let res = TextResponse { body: String::from("<html><body></body></html>\n"), ..res };
+        assert_eq!(retry_condition_of(&res), None);
+
+        let res = TextResponse { body: String::from(" \n"), ..res };
+        assert_eq!(retry_condition_of(&res), None);
+
+        let res = TextResponse { status: reqwest::StatusCode::BAD_GATEWAY, ..res };
+        assert_eq!(retry_condition_of(&res), Some(RetryCondition::ServerError));
+
+        let res = TextResponse {
+            status: reqwest::StatusCode::OK,
+            content_type: String::from("application/json"),
+            body: String::from("{}"),
+        };
+        assert_eq!(retry_condition_of(&res), None);
+    }
+
     #[tokio::test]
     async fn sign_in_test() {
         let engine = EhClient::new();
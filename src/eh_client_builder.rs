@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use crate::{
+    EhResult,
+    eh_client::EhClient,
+    eh_retry::RetryPolicy,
+    eh_session::EhSession,
+    eh_throttle::EhThrottle,
+    settings::{GallerySites, Settings},
+};
+
+/// Configures the http client behind an `EhClient`.
+///
+/// ```ignore
+/// let client = EhClient::builder()
+///     .site(GallerySites::EX)
+///     .connect_timeout(Duration::from_secs(10))
+///     .proxy("socks5h://127.0.0.1:1080")
+///     .built_in_hosts()
+///     .build()?;
+/// ```
+#[derive(Debug, Default)]
+pub struct EhClientBuilder {
+    settings: Settings,
+    session_opt: Option<Arc<EhSession>>,
+    throttle_opt: Option<Arc<EhThrottle>>,
+    retry_policy: RetryPolicy,
+    connect_timeout_opt: Option<Duration>,
+    request_timeout_opt: Option<Duration>,
+    proxy_opt: Option<String>,
+    user_agent_opt: Option<String>,
+    default_headers: HeaderMap,
+    host_map: HashMap<String, Vec<IpAddr>>,
+}
+
+impl EhClientBuilder {
+    pub fn new() -> EhClientBuilder {
+        EhClientBuilder::default()
+    }
+
+    pub fn settings(mut self, settings: Settings) -> EhClientBuilder {
+        self.settings = settings;
+        self
+    }
+
+    pub fn site(mut self, site: GallerySites) -> EhClientBuilder {
+        self.settings.set_site(site);
+        self
+    }
+
+    /// Defaults to an empty session.
+    pub fn session(mut self, session: Arc<EhSession>) -> EhClientBuilder {
+        self.session_opt = Some(session);
+        self
+    }
+
+    /// Clients sending from the same address should share one throttle.
+    pub fn throttle(mut self, throttle: Arc<EhThrottle>) -> EhClientBuilder {
+        self.throttle_opt = Some(throttle);
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> EhClientBuilder {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> EhClientBuilder {
+        self.connect_timeout_opt = Some(timeout);
+        self
+    }
+
+    /// Limits the whole exchange, from sending the request to the end of the body.
+    pub fn request_timeout(mut self, timeout: Duration) -> EhClientBuilder {
+        self.request_timeout_opt = Some(timeout);
+        self
+    }
+
+    /// `http://`, `https://`, `socks5://` or `socks5h://`, the latter resolves
+    /// host names on the proxy.
+    pub fn proxy(mut self, url: &str) -> EhClientBuilder {
+        self.proxy_opt = Some(String::from(url));
+        self
+    }
+
+    pub fn user_agent(mut self, user_agent: &str) -> EhClientBuilder {
+        self.user_agent_opt = Some(String::from(user_agent));
+        self
+    }
+
+    /// Sent with every request, unless the request sets the same header itself.
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> EhClientBuilder {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    /// Connect to `ips` instead of resolving `domain`, bypassing a poisoned DNS.
+    pub fn host(mut self, domain: &str, ips: &[IpAddr]) -> EhClientBuilder {
+        self.host_map.insert(String::from(domain), ips.to_vec());
+        self
+    }
+
+    /// Add the hosts EhViewer ships with, for any domain not set with `host` yet.
+    ///
+    /// These addresses change over time, prefer `host` with fresh ones when they stop working.
+    pub fn built_in_hosts(mut self) -> EhClientBuilder {
+        for (domain, ips) in BUILT_IN_HOSTS {
+            if self.host_map.contains_key(domain) {
+                continue;
+            }
+
+            let ip_vec = ips.iter()
+                .map(|ip| ip.parse::<IpAddr>().unwrap())
+                .collect::<Vec<IpAddr>>();
+            self.host_map.insert(String::from(domain), ip_vec);
+        }
+
+        self
+    }
+
+    pub fn build(self) -> EhResult<EhClient> {
+        let session = self.session_opt.unwrap_or_default();
+        let throttle = self.throttle_opt.unwrap_or_default();
+
+        let mut builder = reqwest::Client::builder()
+            .cookie_provider(session.clone())
+            .referer(true)
+            .default_headers(self.default_headers);
+
+        if let Some(timeout) = self.connect_timeout_opt {
+            builder = builder.connect_timeout(timeout);
+        }
+
+        if let Some(timeout) = self.request_timeout_opt {
+            builder = builder.timeout(timeout);
+        }
+
+        if let Some(proxy) = self.proxy_opt {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+
+        if let Some(user_agent) = self.user_agent_opt {
+            builder = builder.user_agent(user_agent);
+        }
+
+        for (domain, ip_vec) in self.host_map {
+            // the port is ignored, the one of the url is used.
+            let addr_vec = ip_vec.into_iter()
+                .map(|ip| SocketAddr::new(ip, 0))
+                .collect::<Vec<SocketAddr>>();
+            builder = builder.resolve_to_addrs(&domain, &addr_vec);
+        }
+
+        let client = builder.build()?;
+        Ok(EhClient::from_parts(client, self.settings, session, throttle, self.retry_policy))
+    }
+}
+
+const BUILT_IN_HOSTS: [(&str, &[&str]); 4] = [
+    ("e-hentai.org", &["104.20.134.21", "104.20.135.21", "172.67.0.127"]),
+    ("forums.e-hentai.org", &["94.100.18.243"]),
+    ("exhentai.org", &["178.175.128.252", "178.175.128.254", "178.175.129.252", "178.175.129.254", "178.175.132.20", "178.175.132.22"]),
+    ("ehgt.org", &["37.48.89.44", "81.171.10.48", "178.162.139.24", "178.162.140.212"]),
+];
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use crate::EhError;
+    use super::*;
+
+    #[tokio::test]
+    async fn build_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0; 4096];
+            let n = stream.read(&mut buf).await.unwrap();
+
+            let body = "Your IP address has been temporarily banned for excessive pageloads. The ban expires in 1 minute";
+            let res = format!("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+            stream.write_all(res.as_bytes()).await.unwrap();
+
+            String::from_utf8_lossy(&buf[..n]).to_lowercase()
+        });
+
+        let client = EhClientBuilder::new()
+            .user_agent("ehviewer-x-test")
+            .default_header(HeaderName::from_static("x-test"), HeaderValue::from_static("1"))
+            .host("e-hentai.org", &["127.0.0.1".parse().unwrap()])
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+
+        let result = client.get_gallery_list(&format!("http://e-hentai.org:{}/", port)).await;
+        assert!(matches!(result, Err(EhError::IpBanned { .. })));
+
+        let request = server.await.unwrap();
+        assert!(request.contains("user-agent: ehviewer-x-test"));
+        assert!(request.contains("x-test: 1"));
+        assert!(request.contains(&format!("host: e-hentai.org:{}", port)));
+    }
+
+    #[test]
+    fn built_in_hosts_test() {
+        let ip = "127.0.0.1".parse::<IpAddr>().unwrap();
+        let builder = EhClientBuilder::new()
+            .host("exhentai.org", &[ip])
+            .built_in_hosts();
+
+        assert_eq!(builder.host_map["exhentai.org"], vec![ip]);
+        assert!(builder.host_map.contains_key("ehgt.org"));
+    }
+}
@@ -291,6 +291,10 @@ pub const CONTENT_WARNING_SHOW: &str = "0";
  * not show warning
  */
 pub const CONTENT_WARNING_NOT_SHOW: &str = "1";
+/**
+ * not show warning, and remember it for the account
+ */
+pub const CONTENT_WARNING_NOT_SHOW_ALWAYS: &str = "always";
 /**
  * Default gallery_list title
  */
@@ -528,6 +532,7 @@ pub static lofiResolution: Lazy<Mutex<&str>> = Lazy::new(|| Mutex::new(LOFI_RESO
 /**
  * Show content warning
  * key: {@link #KEY_CONTENT_WARNING}<br/>
- * value: {@link #CONTENT_WARNING_SHOW}, {@link #CONTENT_WARNING_NOT_SHOW}
+ * value: {@link #CONTENT_WARNING_SHOW}, {@link #CONTENT_WARNING_NOT_SHOW},
+ * {@link #CONTENT_WARNING_NOT_SHOW_ALWAYS}
  */
 pub static contentWarning: Lazy<Mutex<&str>> = Lazy::new(|| Mutex::new(CONTENT_WARNING_NOT_SHOW));
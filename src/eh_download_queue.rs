@@ -0,0 +1,406 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use crate::{
+    EhResult,
+    eh_downloader::{DownloadControl, DownloadEvent, DownloadSummary, Downloader},
+    parser::ParseError,
+    structures::{GalleryIdentity, GalleryPageUrl},
+};
+
+/// Gallery downloads waiting their turn, saved to a JSON file after every
+/// change so that a restart resumes where the last run stopped.
+///
+/// Jobs run one at a time through `run_next`, the highest priority first,
+/// in queue order among equal priorities.
+#[derive(Debug)]
+pub struct DownloadQueue {
+    path: PathBuf,
+    queue_file: Mutex<QueueFile>,
+    /// The downloads in progress, by job id.
+    control_map: Mutex<HashMap<u64, DownloadControl>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DownloadJob {
+    pub id: u64,
+    pub identity: GalleryIdentity,
+    pub dir: PathBuf,
+    /// Higher runs first.
+    pub priority: i32,
+    pub state: JobState,
+    /// Empty until the job runs for the first time.
+    pub page_url_vec: Vec<GalleryPageUrl>,
+    /// The pages whose file is in `dir`, 0-based.
+    pub done_page_set: BTreeSet<u32>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Failed { reason: String },
+    Done,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct QueueFile {
+    next_id: u64,
+    job_vec: Vec<DownloadJob>,
+}
+
+impl DownloadJob {
+    /// `(done, pages)`, pages is 0 until the page urls are known.
+    pub fn progress(&self) -> (u32, u32) {
+        (self.done_page_set.len() as u32, self.page_url_vec.len() as u32)
+    }
+}
+
+impl DownloadQueue {
+    /// Restore the queue saved at `path`, or start an empty one.
+    ///
+    /// Jobs that were running when the queue was last saved are queued again.
+    pub fn open<P: AsRef<Path>>(path: P) -> EhResult<DownloadQueue> {
+        let path = path.as_ref().to_path_buf();
+        let mut queue_file = if path.exists() {
+            let s = fs::read_to_string(&path)?;
+            serde_json::from_str::<QueueFile>(&s).map_err(ParseError::from)?
+        } else {
+            QueueFile::default()
+        };
+
+        for job in queue_file.job_vec.iter_mut().filter(|job| job.state == JobState::Running) {
+            job.state = JobState::Queued;
+        }
+
+        Ok(DownloadQueue {
+            path,
+            queue_file: Mutex::new(queue_file),
+            control_map: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Queue a gallery to be downloaded into `dir`, returns the job id.
+    pub fn push<P: AsRef<Path>>(&self, identity: GalleryIdentity, dir: P, priority: i32) -> EhResult<u64> {
+        let mut queue_file = self.queue_file.lock().unwrap();
+        let id = queue_file.next_id;
+        queue_file.next_id += 1;
+        queue_file.job_vec.push(DownloadJob {
+            id,
+            identity,
+            dir: dir.as_ref().to_path_buf(),
+            priority,
+            state: JobState::Queued,
+            page_url_vec: vec![],
+            done_page_set: BTreeSet::new(),
+        });
+
+        self.save(&queue_file)?;
+        Ok(id)
+    }
+
+    /// Every job, in queue order.
+    pub fn jobs(&self) -> Vec<DownloadJob> {
+        self.queue_file.lock().unwrap().job_vec.clone()
+    }
+
+    pub fn job(&self, id: u64) -> Option<DownloadJob> {
+        self.queue_file.lock().unwrap().job_vec.iter().find(|job| job.id == id).cloned()
+    }
+
+    /// The job `run_next` would start.
+    pub fn next_job(&self) -> Option<DownloadJob> {
+        let queue_file = self.queue_file.lock().unwrap();
+        next_index(&queue_file.job_vec).map(|index| queue_file.job_vec[index].clone())
+    }
+
+    /// False if there is no job `id`.
+    pub fn set_priority(&self, id: u64, priority: i32) -> EhResult<bool> {
+        self.update(id, |job| job.priority = priority)
+    }
+
+    /// Move the job `id` to `index` in the queue order, or to the end if out of range.
+    pub fn move_job(&self, id: u64, index: usize) -> EhResult<bool> {
+        let mut queue_file = self.queue_file.lock().unwrap();
+        let Some(from) = queue_file.job_vec.iter().position(|job| job.id == id) else {
+            return Ok(false);
+        };
+
+        let job = queue_file.job_vec.remove(from);
+        let index = index.min(queue_file.job_vec.len());
+        queue_file.job_vec.insert(index, job);
+
+        self.save(&queue_file)?;
+        Ok(true)
+    }
+
+    /// Keep a job from running, or stop it after the pages in flight.
+    pub fn pause(&self, id: u64) -> EhResult<bool> {
+        let paused = self.update(id, |job| {
+            if matches!(job.state, JobState::Queued | JobState::Running) {
+                job.state = JobState::Paused;
+            }
+        })?;
+
+        if let Some(control) = self.control_map.lock().unwrap().get(&id) {
+            control.cancel();
+        }
+
+        Ok(paused)
+    }
+
+    /// Queue a paused or failed job again.
+    pub fn resume(&self, id: u64) -> EhResult<bool> {
+        self.update(id, |job| {
+            if matches!(job.state, JobState::Paused | JobState::Failed { .. }) {
+                job.state = JobState::Queued;
+            }
+        })
+    }
+
+    /// Forget the job `id`, stopping it if it is running. The files are kept.
+    pub fn remove(&self, id: u64) -> EhResult<Option<DownloadJob>> {
+        let job_opt = {
+            let mut queue_file = self.queue_file.lock().unwrap();
+            match queue_file.job_vec.iter().position(|job| job.id == id) {
+                Some(index) => {
+                    let job = queue_file.job_vec.remove(index);
+                    self.save(&queue_file)?;
+                    Some(job)
+                }
+                None => None,
+            }
+        };
+
+        // after the job is gone, see `run_next`.
+        if let Some(control) = self.control_map.lock().unwrap().remove(&id) {
+            control.cancel();
+        }
+
+        Ok(job_opt)
+    }
+
+    /// Run the next queued job to its end, returns its id, or `None` if
+    /// nothing is queued.
+    ///
+    /// Errors are those of the queue file, a job that fails to download is
+    /// marked `JobState::Failed` instead.
+    pub async fn run_next(&self, downloader: &Downloader) -> EhResult<Option<u64>> {
+        let Some(job) = self.start_next()? else {
+            return Ok(None);
+        };
+
+        let id = job.id;
+        self.run_job(job, downloader).await?;
+        Ok(Some(id))
+    }
+
+    /// Run queued jobs until none is left.
+    pub async fn run_all(&self, downloader: &Downloader) -> EhResult<()> {
+        while self.run_next(downloader).await?.is_some() {}
+        Ok(())
+    }
+
+    /// Download a job marked as running by `start_next`.
+    async fn run_job(&self, mut job: DownloadJob, downloader: &Downloader) -> EhResult<()> {
+        if job.page_url_vec.is_empty() {
+            match downloader.client().all_page_urls(&job.identity).await {
+                Ok(page_url_vec) => {
+                    self.update(job.id, |job| job.page_url_vec = page_url_vec.clone())?;
+                    job.page_url_vec = page_url_vec;
+                }
+                Err(e) => return self.finish(job.id, Err(e.to_string())),
+            }
+        }
+
+        let pages = job.page_url_vec.len() as u32;
+        let remaining_page_url_vec = job.page_url_vec.into_iter()
+            .filter(|page_url| !job.done_page_set.contains(&page_url.page))
+            .collect();
+
+        // `pause` and `remove` change the job before they look for its control,
+        // so checking the state under the map lock leaves no window.
+        let (handle, mut event_rx) = {
+            let mut control_map = self.control_map.lock().unwrap();
+            if !self.is_running(job.id) {
+                return Ok(());
+            }
+
+            let (handle, event_rx) = downloader.download_pages(remaining_page_url_vec, pages, &job.dir);
+            control_map.insert(job.id, handle.control());
+            (handle, event_rx)
+        };
+
+        let join = handle.join();
+        tokio::pin!(join);
+        let result = loop {
+            tokio::select! {
+                biased;
+                Some(event) = event_rx.recv() => self.apply_event(job.id, event)?,
+                result = &mut join => break result,
+            }
+        };
+
+        while let Ok(event) = event_rx.try_recv() {
+            self.apply_event(job.id, event)?;
+        }
+
+        self.control_map.lock().unwrap().remove(&job.id);
+        self.finish(job.id, result.map_err(|e| e.to_string()))
+    }
+
+    /// Mark the next job as running.
+    fn start_next(&self) -> EhResult<Option<DownloadJob>> {
+        let mut queue_file = self.queue_file.lock().unwrap();
+        let Some(index) = next_index(&queue_file.job_vec) else {
+            return Ok(None);
+        };
+
+        queue_file.job_vec[index].state = JobState::Running;
+        let job = queue_file.job_vec[index].clone();
+
+        self.save(&queue_file)?;
+        Ok(Some(job))
+    }
+
+    fn is_running(&self, id: u64) -> bool {
+        self.queue_file.lock().unwrap().job_vec.iter().any(|job| job.id == id && job.state == JobState::Running)
+    }
+
+    fn apply_event(&self, id: u64, event: DownloadEvent) -> EhResult<()> {
+        match event {
+            DownloadEvent::PageDownloaded { page, .. } | DownloadEvent::PageSkipped { page } => {
+                self.update(id, |job| { job.done_page_set.insert(page); })?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Settle the state of a job whose download ended, unless it was paused meanwhile.
+    fn finish(&self, id: u64, result: Result<DownloadSummary, String>) -> EhResult<()> {
+        self.update(id, |job| {
+            if job.state != JobState::Running {
+                return;
+            }
+
+            job.state = match &result {
+                Err(reason) => JobState::Failed { reason: reason.clone() },
+                Ok(summary) if summary.cancelled => JobState::Paused,
+                Ok(summary) if !summary.failed_page_vec.is_empty() => JobState::Failed {
+                    reason: format!("{} pages failed", summary.failed_page_vec.len()),
+                },
+                Ok(_) => JobState::Done,
+            };
+        })?;
+
+        Ok(())
+    }
+
+    fn update<F: FnOnce(&mut DownloadJob)>(&self, id: u64, f: F) -> EhResult<bool> {
+        let mut queue_file = self.queue_file.lock().unwrap();
+        let Some(job) = queue_file.job_vec.iter_mut().find(|job| job.id == id) else {
+            return Ok(false);
+        };
+
+        f(job);
+        self.save(&queue_file)?;
+        Ok(true)
+    }
+
+    /// Written aside then renamed, a crash never leaves half a file.
+    fn save(&self, queue_file: &QueueFile) -> EhResult<()> {
+        let s = serde_json::to_string_pretty(queue_file).map_err(ParseError::from)?;
+        let partial_path = self.path.with_extension(PARTIAL_EXTENSION);
+        fs::write(&partial_path, s)?;
+        fs::rename(&partial_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+/// The first queued job of the highest priority.
+fn next_index(job_vec: &[DownloadJob]) -> Option<usize> {
+    job_vec.iter()
+        .enumerate()
+        .filter(|(_, job)| job.state == JobState::Queued)
+        .min_by_key(|(index, job)| (-(job.priority as i64), *index))
+        .map(|(index, _)| index)
+}
+
+const PARTIAL_EXTENSION: &str = "part";
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use crate::eh_client::EhClient;
+    use super::*;
+
+    fn identity(gid: u64) -> GalleryIdentity {
+        GalleryIdentity { gid, token: String::from("588c82702b") }
+    }
+
+    #[test]
+    fn queue_test() {
+        let path = std::env::temp_dir().join(format!("eh_download_queue_{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let queue = DownloadQueue::open(&path).unwrap();
+        let a = queue.push(identity(1), "a", 0).unwrap();
+        let b = queue.push(identity(2), "b", 0).unwrap();
+        let c = queue.push(identity(3), "c", 1).unwrap();
+        assert_eq!(queue.next_job().unwrap().id, c);
+
+        queue.set_priority(c, 0).unwrap();
+        queue.move_job(b, 0).unwrap();
+        assert_eq!(queue.jobs().iter().map(|job| job.id).collect::<Vec<u64>>(), vec![b, a, c]);
+        assert_eq!(queue.next_job().unwrap().id, b);
+
+        queue.pause(b).unwrap();
+        assert_eq!(queue.job(b).unwrap().state, JobState::Paused);
+        assert_eq!(queue.start_next().unwrap().unwrap().id, a);
+        queue.apply_event(a, DownloadEvent::PageSkipped { page: 3 }).unwrap();
+        drop(queue);
+
+        let queue = DownloadQueue::open(&path).unwrap();
+        let job = queue.job(a).unwrap();
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.done_page_set, BTreeSet::from([3]));
+        assert_eq!(queue.job(b).unwrap().state, JobState::Paused);
+
+        queue.resume(b).unwrap();
+        assert_eq!(queue.next_job().unwrap().id, b);
+        assert!(queue.remove(a).unwrap().is_some());
+        assert!(queue.job(a).is_none());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pause_before_download_test() {
+        let path = std::env::temp_dir().join(format!("eh_download_queue_pause_{}.json", std::process::id()));
+        let dir = std::env::temp_dir().join(format!("eh_download_queue_pause_{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let queue = DownloadQueue::open(&path).unwrap();
+        let id = queue.push(identity(1), &dir, 0).unwrap();
+        let page_url = GalleryPageUrl { gid: 1, p_token: String::from("0000000000"), page: 0 };
+        queue.update(id, |job| job.page_url_vec = vec![page_url]).unwrap();
+
+        // paused while the page urls were loading.
+        let job = queue.start_next().unwrap().unwrap();
+        queue.pause(id).unwrap();
+
+        let downloader = Downloader::new(Arc::new(EhClient::new()));
+        queue.run_job(job, &downloader).await.unwrap();
+        assert_eq!(queue.job(id).unwrap().state, JobState::Paused);
+        assert!(queue.control_map.lock().unwrap().is_empty());
+        assert!(!dir.exists());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
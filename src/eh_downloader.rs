@@ -0,0 +1,791 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use futures::StreamExt;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use crate::{
+    EhError,
+    EhResult,
+    eh_client::EhClient,
+    eh_cbz::{CbzWriter, ComicInfo},
+    eh_config,
+    eh_verify::{self, DownloadManifest},
+    structures::{GalleryIdentity, GalleryPage, GalleryPageUrl},
+};
+
+/// Downloads the images of galleries into a directory, one file per page
+/// named by its position, `001.jpg`, `002.png`...
+///
+/// Pages whose file already exists are skipped, so a download stopped for
+/// any reason can be started again on the same directory.
+#[derive(Debug, Clone)]
+pub struct Downloader {
+    client: Arc<EhClient>,
+    concurrency: usize,
+    always_original: bool,
+    max_reloads: u32,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum DownloadState {
+    Running,
+    Paused,
+    Cancelled,
+}
+
+/// Sent over the channel returned with the `DownloadHandle`, pages are 0-based.
+#[derive(Debug)]
+pub enum DownloadEvent {
+    Started { pages: u32 },
+    /// `path` is the file written, or the archive of a CBZ download.
+    PageDownloaded { page: u32, path: PathBuf },
+    /// The file of the page was already in the directory.
+    PageSkipped { page: u32 },
+    PageFailed { page: u32, error: EhError },
+    /// The download paused itself, it will retry `page` once resumed.
+    QuotaExceeded { page: u32 },
+    Paused,
+    Resumed,
+    Cancelled,
+    Finished(DownloadSummary),
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct DownloadSummary {
+    pub downloaded: u32,
+    pub skipped: u32,
+    pub failed_page_vec: Vec<u32>,
+    pub cancelled: bool,
+}
+
+/// A running download, see `DownloadControl` to pause it.
+#[derive(Debug)]
+pub struct DownloadHandle {
+    control: DownloadControl,
+    join_handle: JoinHandle<EhResult<DownloadSummary>>,
+}
+
+/// Pauses, resumes or cancels a running download, can be cloned and kept
+/// after the `DownloadHandle` is joined.
+///
+/// Pausing and cancelling take effect between pages: the images already
+/// being fetched are finished first.
+#[derive(Debug, Clone)]
+pub struct DownloadControl {
+    state_tx: Arc<watch::Sender<DownloadState>>,
+    event_tx: mpsc::UnboundedSender<DownloadEvent>,
+}
+
+struct DownloadContext {
+    client: Arc<EhClient>,
+    target: PageTarget,
+    always_original: bool,
+    max_reloads: u32,
+    pages: u32,
+    existing_file_map: HashMap<String, PathBuf>,
+    state_tx: Arc<watch::Sender<DownloadState>>,
+    event_tx: mpsc::UnboundedSender<DownloadEvent>,
+}
+
+enum PageSource {
+    Gallery(GalleryIdentity),
+    /// The urls to download, and the page count of the gallery.
+    PageUrls(Vec<GalleryPageUrl>, u32),
+}
+
+enum DownloadTarget {
+    Dir(PathBuf),
+    Cbz { path: PathBuf, comic_info: Box<ComicInfo> },
+}
+
+/// Where the pages are written while downloading.
+enum PageTarget {
+    Dir(PathBuf),
+    /// `None` once finished. Only used in `spawn_blocking`, zip writes block.
+    Cbz { path: PathBuf, writer: Arc<Mutex<Option<CbzWriter>>> },
+}
+
+enum PageOutcome {
+    Downloaded { page: u32, resampled: bool },
+    Skipped,
+    Failed(u32),
+    Cancelled,
+}
+
+impl Downloader {
+    pub fn new(client: Arc<EhClient>) -> Downloader {
+        Downloader {
+            client,
+            concurrency: DEFAULT_CONCURRENCY,
+            always_original: *eh_config::alwaysOriginal.lock().unwrap() == eh_config::ALWAYS_ORIGINAL_YES,
+            max_reloads: DEFAULT_MAX_RELOADS,
+        }
+    }
+
+    /// How many pages are downloaded at once, at least 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Downloader {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Download the original images when the page offers them, instead of the
+    /// resampled ones. Defaults to `eh_config::alwaysOriginal`.
+    pub fn always_original(mut self, always_original: bool) -> Downloader {
+        self.always_original = always_original;
+        self
+    }
+
+    /// How many times a page is loaded again with `nl`, from another H@H node,
+    /// when its image cannot be downloaded.
+    pub fn max_reloads(mut self, max_reloads: u32) -> Downloader {
+        self.max_reloads = max_reloads;
+        self
+    }
+
+    pub fn client(&self) -> &Arc<EhClient> {
+        &self.client
+    }
+
+    /// Start downloading the gallery into `dir`, which is created if needed.
+    pub fn download<P: AsRef<Path>>(&self, identity: GalleryIdentity, dir: P) -> (DownloadHandle, mpsc::UnboundedReceiver<DownloadEvent>) {
+        self.spawn(PageSource::Gallery(identity), DownloadTarget::Dir(dir.as_ref().to_path_buf()))
+    }
+
+    /// Start downloading some pages of a gallery of `pages` pages, whose urls
+    /// are already known, into `dir`.
+    pub fn download_pages<P: AsRef<Path>>(&self, page_url_vec: Vec<GalleryPageUrl>, pages: u32, dir: P) -> (DownloadHandle, mpsc::UnboundedReceiver<DownloadEvent>) {
+        self.spawn(PageSource::PageUrls(page_url_vec, pages), DownloadTarget::Dir(dir.as_ref().to_path_buf()))
+    }
+
+    /// Start downloading the gallery straight into a CBZ at `path`, without
+    /// a folder in between.
+    ///
+    /// The archive is written to `<path>.part` and only renamed to `path` once
+    /// every page is in it. Unlike a folder, it cannot be resumed: the partial
+    /// archive is removed if a page fails or the download is cancelled.
+    pub fn download_cbz<P: AsRef<Path>>(&self, identity: GalleryIdentity, comic_info: ComicInfo, path: P) -> (DownloadHandle, mpsc::UnboundedReceiver<DownloadEvent>) {
+        let path = path.as_ref().to_path_buf();
+        self.spawn(PageSource::Gallery(identity), DownloadTarget::Cbz { path, comic_info: Box::new(comic_info) })
+    }
+
+    fn spawn(&self, source: PageSource, target: DownloadTarget) -> (DownloadHandle, mpsc::UnboundedReceiver<DownloadEvent>) {
+        let (state_tx, _) = watch::channel(DownloadState::Running);
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let control = DownloadControl { state_tx: Arc::new(state_tx), event_tx };
+
+        let downloader = self.clone();
+        let join_handle = tokio::spawn(downloader.run(source, target, control.clone()));
+
+        (DownloadHandle { control, join_handle }, event_rx)
+    }
+
+    async fn run(self, source: PageSource, target: DownloadTarget, control: DownloadControl) -> EhResult<DownloadSummary> {
+        let DownloadControl { state_tx, event_tx } = control;
+
+        let (page_url_vec, pages) = match source {
+            PageSource::Gallery(identity) => {
+                let page_url_vec = self.client.all_page_urls(&identity).await?;
+                let pages = page_url_vec.len() as u32;
+                (page_url_vec, pages)
+            }
+            PageSource::PageUrls(page_url_vec, pages) => (page_url_vec, pages),
+        };
+        let _ = event_tx.send(DownloadEvent::Started { pages });
+
+        let (target, mut manifest_opt, existing_file_map) = match target {
+            DownloadTarget::Dir(dir) => {
+                tokio::fs::create_dir_all(&dir).await?;
+
+                let gid_opt = page_url_vec.first().map(|page_url| page_url.gid);
+                let mut manifest = DownloadManifest::load(&dir)?.unwrap_or_default();
+                // its files would be taken for pages of this gallery.
+                if gid_opt.is_some_and(|gid| manifest.gid != 0 && manifest.gid != gid) {
+                    let error = format!("{} holds the pages of gallery {}", dir.display(), manifest.gid);
+                    return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, error).into());
+                }
+
+                manifest.add_page_urls(&page_url_vec, pages);
+                manifest.save(&dir)?;
+
+                let existing_file_map = existing_file_map(&dir)?;
+                (PageTarget::Dir(dir.clone()), Some((manifest, dir)), existing_file_map)
+            }
+            DownloadTarget::Cbz { path, comic_info } => {
+                let partial_path = partial_path_of(&path);
+                let writer = tokio::task::spawn_blocking(move || CbzWriter::create(partial_path, &comic_info))
+                    .await
+                    .map_err(std::io::Error::other)??;
+                (PageTarget::Cbz { path, writer: Arc::new(Mutex::new(Some(writer))) }, None, HashMap::new())
+            }
+        };
+
+        let context = Arc::new(DownloadContext {
+            client: self.client.clone(),
+            target,
+            existing_file_map,
+            always_original: self.always_original,
+            max_reloads: self.max_reloads,
+            pages,
+            state_tx,
+            event_tx: event_tx.clone(),
+        });
+
+        let mut outcome_stream = futures::stream::iter(page_url_vec)
+            .map(|page_url| download_page(context.clone(), page_url))
+            .buffer_unordered(self.concurrency);
+
+        let mut summary = DownloadSummary::default();
+        while let Some(outcome) = outcome_stream.next().await {
+            match outcome {
+                PageOutcome::Downloaded { page, resampled } => {
+                    summary.downloaded += 1;
+                    if let Some((manifest, dir)) = &mut manifest_opt {
+                        manifest.set_resampled(page, resampled);
+                        // saved again below, whose error is the one returned.
+                        let _ = manifest.save(dir);
+                    }
+                }
+                PageOutcome::Skipped => summary.skipped += 1,
+                PageOutcome::Failed(page) => summary.failed_page_vec.push(page),
+                PageOutcome::Cancelled => summary.cancelled = true,
+            }
+        }
+
+        summary.failed_page_vec.sort();
+
+        let result = match (&context.target, manifest_opt) {
+            (PageTarget::Dir(_), Some((manifest, dir))) => manifest.save(dir),
+            (PageTarget::Cbz { path, writer }, _) => finish_cbz(path, writer, &summary).await,
+            _ => Ok(()),
+        };
+
+        let _ = event_tx.send(DownloadEvent::Finished(summary.clone()));
+        result.map(|_| summary)
+    }
+}
+
+impl DownloadHandle {
+    pub fn control(&self) -> DownloadControl {
+        self.control.clone()
+    }
+
+    pub fn state(&self) -> DownloadState {
+        self.control.state()
+    }
+
+    pub fn pause(&self) {
+        self.control.pause();
+    }
+
+    pub fn resume(&self) {
+        self.control.resume();
+    }
+
+    pub fn cancel(&self) {
+        self.control.cancel();
+    }
+
+    /// Wait for the download to end, fails only if the gallery itself could
+    /// not be loaded, or its manifest or archive could not be written. The
+    /// pages that failed are listed in the summary.
+    pub async fn join(self) -> EhResult<DownloadSummary> {
+        match self.join_handle.await {
+            Ok(result) => result,
+            Err(e) => Err(EhError::IoError(std::io::Error::other(e))),
+        }
+    }
+}
+
+impl DownloadControl {
+    pub fn state(&self) -> DownloadState {
+        *self.state_tx.borrow()
+    }
+
+    pub fn pause(&self) {
+        if self.state_tx.send_if_modified(|state| transition(state, DownloadState::Running, DownloadState::Paused)) {
+            let _ = self.event_tx.send(DownloadEvent::Paused);
+        }
+    }
+
+    pub fn resume(&self) {
+        if self.state_tx.send_if_modified(|state| transition(state, DownloadState::Paused, DownloadState::Running)) {
+            let _ = self.event_tx.send(DownloadEvent::Resumed);
+        }
+    }
+
+    pub fn cancel(&self) {
+        let cancelled = self.state_tx.send_if_modified(|state| {
+            let modified = *state != DownloadState::Cancelled;
+            *state = DownloadState::Cancelled;
+            modified
+        });
+
+        if cancelled {
+            let _ = self.event_tx.send(DownloadEvent::Cancelled);
+        }
+    }
+}
+
+/// The file name of `page` without extension, padded to the width of `pages`.
+pub fn page_file_stem(page: u32, pages: u32) -> String {
+    let width = pages.to_string().len().max(MIN_STEM_WIDTH);
+    format!("{:0width$}", page + 1, width = width)
+}
+
+/// The page files of a folder written by the `Downloader`, in page order.
+pub fn downloaded_page_vec<P: AsRef<Path>>(dir: P) -> EhResult<Vec<PathBuf>> {
+    let mut page_vec = existing_file_map(dir.as_ref())?
+        .into_iter()
+        .filter_map(|(stem, path)| stem.parse::<u32>().ok().map(|position| (position, path)))
+        .collect::<Vec<(u32, PathBuf)>>();
+    page_vec.sort_by_key(|(position, _)| *position);
+
+    Ok(page_vec.into_iter().map(|(_, path)| path).collect())
+}
+
+/// `path` with `.part` appended.
+fn partial_path_of(path: &Path) -> PathBuf {
+    let mut partial_path = path.as_os_str().to_owned();
+    partial_path.push(".");
+    partial_path.push(PARTIAL_EXTENSION);
+    PathBuf::from(partial_path)
+}
+
+fn transition(state: &mut DownloadState, from: DownloadState, to: DownloadState) -> bool {
+    if *state != from {
+        return false;
+    }
+
+    *state = to;
+    true
+}
+
+/// The files of `dir` by file stem, except the partial ones.
+pub(crate) fn existing_file_map(dir: &Path) -> EhResult<HashMap<String, PathBuf>> {
+    let mut file_map = HashMap::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|extension| extension == PARTIAL_EXTENSION) {
+            continue;
+        }
+
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            file_map.insert(String::from(stem), path.clone());
+        }
+    }
+
+    Ok(file_map)
+}
+
+/// Wait while the download is paused, false once it is cancelled.
+async fn wait_running(state_tx: &watch::Sender<DownloadState>) -> bool {
+    let mut state_rx = state_tx.subscribe();
+    loop {
+        let state = *state_rx.borrow_and_update();
+        match state {
+            DownloadState::Running => return true,
+            DownloadState::Cancelled => return false,
+            DownloadState::Paused => {}
+        }
+
+        if state_rx.changed().await.is_err() {
+            return false;
+        }
+    }
+}
+
+async fn download_page(context: Arc<DownloadContext>, page_url: GalleryPageUrl) -> PageOutcome {
+    let page = page_url.page;
+    let stem = page_file_stem(page, context.pages);
+    if context.existing_file_map.contains_key(&stem) {
+        let _ = context.event_tx.send(DownloadEvent::PageSkipped { page });
+        return PageOutcome::Skipped;
+    }
+
+    loop {
+        if !wait_running(&context.state_tx).await {
+            return PageOutcome::Cancelled;
+        }
+
+        match fetch_page(&context, &page_url, &stem).await {
+            Ok((path, resampled)) => {
+                let _ = context.event_tx.send(DownloadEvent::PageDownloaded { page, path });
+                return PageOutcome::Downloaded { page, resampled };
+            }
+            Err(EhError::ImageQuotaExceeded) => {
+                context.state_tx.send_if_modified(|state| transition(state, DownloadState::Running, DownloadState::Paused));
+                let _ = context.event_tx.send(DownloadEvent::QuotaExceeded { page });
+            }
+            Err(error) => {
+                let _ = context.event_tx.send(DownloadEvent::PageFailed { page, error });
+                return PageOutcome::Failed(page);
+            }
+        }
+    }
+}
+
+/// Returns the path written, and whether it is the resampled image.
+///
+/// The original image is checked against the page token, a mismatch is
+/// retried like a broken image.
+async fn fetch_page(context: &DownloadContext, page_url: &GalleryPageUrl, stem: &str) -> EhResult<(PathBuf, bool)> {
+    let mut page = context.client.get_page(page_url).await?;
+
+    let mut reloads = 0;
+    let (bytes, resampled) = loop {
+        let (image_url, resampled) = image_url_of(&page, context.always_original);
+        let result = match context.client.get_image(image_url).await {
+            Ok(bytes) if !resampled && !eh_verify::matches_p_token(&bytes, &page_url.p_token) => {
+                Err(EhError::HashMismatch {
+                    expected: page_url.p_token.clone(),
+                    actual: eh_verify::sha1_prefix(&bytes),
+                })
+            }
+            result => result,
+        };
+
+        match result {
+            Ok(bytes) => break (bytes, resampled),
+            // another node would not help.
+            Err(e @ (EhError::ImageQuotaExceeded | EhError::IpBanned { .. })) => return Err(e),
+            Err(_) if reloads < context.max_reloads => {
+                reloads += 1;
+                page = context.client.reload_page(page_url, &page).await?;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    // the resampled image is always a JPEG, whatever the original was.
+    let extension = if resampled {
+        String::from(DEFAULT_EXTENSION)
+    } else {
+        page.extension_opt().unwrap_or_else(|| String::from(DEFAULT_EXTENSION))
+    };
+    let filename = format!("{}.{}", stem, extension);
+    let path = match &context.target {
+        PageTarget::Dir(dir) => {
+            let path = dir.join(filename);
+            let partial_path = path.with_extension(PARTIAL_EXTENSION);
+
+            tokio::fs::write(&partial_path, bytes).await?;
+            tokio::fs::rename(&partial_path, &path).await?;
+            path
+        }
+        PageTarget::Cbz { path, writer } => {
+            let writer = writer.clone();
+            tokio::task::spawn_blocking(move || match writer.lock().unwrap().as_mut() {
+                Some(writer) => writer.add_page(&filename, &bytes),
+                None => Ok(()),
+            }).await.map_err(std::io::Error::other)??;
+            path.clone()
+        }
+    };
+
+    Ok((path, resampled))
+}
+
+/// Rename the archive to `path` if every page is in it, remove it otherwise.
+async fn finish_cbz(path: &Path, writer: &Arc<Mutex<Option<CbzWriter>>>, summary: &DownloadSummary) -> EhResult<()> {
+    let partial_path = partial_path_of(path);
+    let complete = summary.failed_page_vec.is_empty() && !summary.cancelled;
+    let writer_opt = writer.lock().unwrap().take();
+    let mut result = match writer_opt {
+        Some(writer) => tokio::task::spawn_blocking(move || writer.finish())
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e).into())),
+        None => Ok(()),
+    };
+
+    if result.is_ok() && complete {
+        result = tokio::fs::rename(&partial_path, path).await.map_err(EhError::from);
+    }
+
+    if result.is_err() || !complete {
+        let _ = tokio::fs::remove_file(&partial_path).await;
+    }
+
+    result
+}
+
+/// The url to download, and whether it is the resampled image.
+fn image_url_of(page: &GalleryPage, always_original: bool) -> (&str, bool) {
+    match &page.origin_image_url_opt {
+        Some(origin_image_url) if always_original => (origin_image_url, false),
+        Some(_) => (&page.image_url, true),
+        None => (&page.image_url, false),
+    }
+}
+
+const DEFAULT_CONCURRENCY: usize = 3;
+const DEFAULT_MAX_RELOADS: u32 = 2;
+const MIN_STEM_WIDTH: usize = 3;
+const DEFAULT_EXTENSION: &str = "jpg";
+/// Written first, then renamed, so that a file with the page name is always complete.
+const PARTIAL_EXTENSION: &str = "part";
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use crate::{eh_retry::RetryPolicy, eh_throttle::EhThrottle};
+    use super::*;
+
+    #[test]
+    fn page_file_stem_test() {
+        assert_eq!(page_file_stem(0, 24), "001");
+        assert_eq!(page_file_stem(1233, 1500), "1234");
+    }
+
+    #[tokio::test]
+    async fn wait_running_test() {
+        let (state_tx, _) = watch::channel(DownloadState::Paused);
+        let state_tx = Arc::new(state_tx);
+
+        let waiter = tokio::spawn({
+            let state_tx = state_tx.clone();
+            async move { wait_running(&state_tx).await }
+        });
+
+        state_tx.send_replace(DownloadState::Running);
+        assert!(waiter.await.unwrap());
+
+        state_tx.send_replace(DownloadState::Cancelled);
+        assert!(!wait_running(&state_tx).await);
+    }
+
+    #[tokio::test]
+    async fn download_test() {
+        let p_token = eh_verify::sha1_prefix(b"page 1");
+        let (host, _) = serve({
+            let p_token = p_token.clone();
+            move |host, path, _| match path {
+                path if path == format!("/s/{}/1-1", p_token) => (200, page_html(host, "1", "a.png", false)),
+                "/s/0000000000/1-3" => (200, page_html(host, "3", "c.png", true)),
+                "/img/1" => (200, b"page 1".to_vec()),
+                "/img/3" => (200, b"page 3".to_vec()),
+                _ => (404, Vec::new()),
+            }
+        }).await;
+
+        let dir = temp_dir("download");
+        fs::write(dir.join("002.jpg"), b"page 2").unwrap();
+
+        let page_url_vec = vec![page_url(0, &p_token), page_url(1, "1111111111"), page_url(2, "0000000000")];
+        let (handle, mut event_rx) = downloader(&host).download_pages(page_url_vec, 3, &dir);
+        let summary = handle.join().await.unwrap();
+        assert_eq!(summary, DownloadSummary { downloaded: 2, skipped: 1, ..DownloadSummary::default() });
+
+        // the original keeps its extension, the resampled image is a JPEG.
+        assert_eq!(fs::read(dir.join("001.png")).unwrap(), b"page 1");
+        assert_eq!(fs::read(dir.join("003.jpg")).unwrap(), b"page 3");
+        assert!(!dir.join("003.png").exists());
+
+        let manifest = DownloadManifest::load(&dir).unwrap().unwrap();
+        assert_eq!(manifest.gid, 1);
+        assert!(manifest.resampled_page_set.contains(&2));
+
+        let event_vec = events(&mut event_rx);
+        assert!(event_vec.iter().any(|event| matches!(event, DownloadEvent::PageSkipped { page: 1 })));
+        assert!(matches!(event_vec.last(), Some(DownloadEvent::Finished(_))));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn reload_on_hash_mismatch_test() {
+        let p_token = eh_verify::sha1_prefix(b"good");
+        let (host, path_vec) = serve(|host, path, _| match path {
+            path if path.starts_with("/s/") && path.contains("?nl=") => (200, page_html(host, "good", "a.jpg", false)),
+            path if path.starts_with("/s/") => (200, page_html(host, "bad", "a.jpg", false)),
+            "/img/good" => (200, b"good".to_vec()),
+            "/img/bad" => (200, b"bad".to_vec()),
+            _ => (404, Vec::new()),
+        }).await;
+
+        let dir = temp_dir("reload");
+        let (handle, _event_rx) = downloader(&host).download_pages(vec![page_url(0, &p_token)], 1, &dir);
+        let summary = handle.join().await.unwrap();
+        assert_eq!(summary.downloaded, 1);
+        assert_eq!(fs::read(dir.join("001.jpg")).unwrap(), b"good");
+        assert!(path_vec.lock().unwrap().iter().any(|path| path.contains("?nl=1-bad")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn quota_pause_test() {
+        let p_token = eh_verify::sha1_prefix(b"page 1");
+        let (host, _) = serve(|host, path, count| match path {
+            path if path.starts_with("/s/") => (200, page_html(host, "1", "a.jpg", false)),
+            "/img/1" if count == 0 => (509, Vec::new()),
+            "/img/1" => (200, b"page 1".to_vec()),
+            _ => (404, Vec::new()),
+        }).await;
+
+        let dir = temp_dir("quota");
+        let (handle, mut event_rx) = downloader(&host).download_pages(vec![page_url(0, &p_token)], 1, &dir);
+        loop {
+            if let DownloadEvent::QuotaExceeded { page } = event_rx.recv().await.unwrap() {
+                assert_eq!(page, 0);
+                break;
+            }
+        }
+
+        assert_eq!(handle.state(), DownloadState::Paused);
+        handle.resume();
+
+        let summary = handle.join().await.unwrap();
+        assert_eq!(summary.downloaded, 1);
+        assert_eq!(fs::read(dir.join("001.jpg")).unwrap(), b"page 1");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn foreign_manifest_test() {
+        let dir = temp_dir("foreign");
+        let manifest = DownloadManifest { gid: 2, ..DownloadManifest::default() };
+        manifest.save(&dir).unwrap();
+        fs::write(dir.join("001.jpg"), b"gallery 2").unwrap();
+
+        let (handle, _event_rx) = downloader("http://127.0.0.1:9/").download_pages(vec![page_url(0, "0000000000")], 1, &dir);
+        assert!(handle.join().await.is_err());
+        assert_eq!(DownloadManifest::load(&dir).unwrap().unwrap().gid, 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn downloader(host: &str) -> Downloader {
+        let mut client = EhClient::builder()
+            .throttle(Arc::new(EhThrottle::new(1000.0, 1000.0)))
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        client.set_host(host);
+
+        Downloader::new(Arc::new(client))
+            .concurrency(1)
+            .always_original(false)
+    }
+
+    fn page_url(page: u32, p_token: &str) -> GalleryPageUrl {
+        GalleryPageUrl { gid: 1, p_token: String::from(p_token), page }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("eh_downloader_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn events(event_rx: &mut mpsc::UnboundedReceiver<DownloadEvent>) -> Vec<DownloadEvent> {
+        let mut event_vec = Vec::new();
+        while let Ok(event) = event_rx.try_recv() {
+            event_vec.push(event);
+        }
+
+        event_vec
+    }
+
+    /// An image page showing `/img/<image>`, with an original to download
+    /// if `resampled`.
+    fn page_html(host: &str, image: &str, filename: &str, resampled: bool) -> Vec<u8> {
+        let origin = if resampled {
+            format!(r#"<div id="i7"><a href="{}fullimg.php?gid=1&amp;page=1&amp;key=k">Download original 2458 x 3497 2.39 MiB source</a></div>"#, host)
+        } else {
+            String::new()
+        };
+
+        format!(r##"
+            <script type="text/javascript">var showkey="a1b2c3d4e5f";</script>
+            <div id="i2"><div>{filename} :: 1280 x 1810 :: 345.2 KiB</div></div>
+            <div id="i3"><img id="img" src="{host}img/{image}" style="height:1810px;width:1280px" /></div>
+            <div id="i6"><a href="#" id="loadfail" onclick="return nl('1-{image}')">Reload broken image</a></div>
+            {origin}
+        "##, filename = filename, host = host, image = image, origin = origin).into_bytes()
+    }
+
+    /// A local server answering `respond(host, path, times the path was asked
+    /// before)`. The `showpage` API gets no answer it can parse, so every page
+    /// is loaded as html.
+    async fn serve<F>(respond: F) -> (String, Arc<Mutex<Vec<String>>>)
+    where
+        F: Fn(&str, &str, usize) -> (u16, Vec<u8>) + Send + Sync + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let host = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+        let path_vec = Arc::new(Mutex::new(Vec::new()));
+        let respond = Arc::new(respond);
+
+        tokio::spawn({
+            let (host, path_vec) = (host.clone(), path_vec.clone());
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (host, path_vec, respond) = (host.clone(), path_vec.clone(), respond.clone());
+                    tokio::spawn(async move {
+                        let Some((stream, path)) = read_request(stream).await else {
+                            return;
+                        };
+
+                        let count = {
+                            let mut path_vec = path_vec.lock().unwrap();
+                            let count = path_vec.iter().filter(|asked| **asked == path).count();
+                            path_vec.push(path.clone());
+                            count
+                        };
+
+                        let (status, body) = match path.as_str() {
+                            "/api.php" => (200, b"{}".to_vec()),
+                            path => respond(&host, path, count),
+                        };
+                        write_response(stream, status, &path, &body).await;
+                    });
+                }
+            }
+        });
+
+        (host, path_vec)
+    }
+
+    /// The path of the request, once its body is read too.
+    async fn read_request(mut stream: TcpStream) -> Option<(TcpStream, String)> {
+        let mut request = Vec::new();
+        let mut buf = [0; 4096];
+        let head_len = loop {
+            if let Some(position) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break position + 4;
+            }
+
+            let n = stream.read(&mut buf).await.ok().filter(|n| *n > 0)?;
+            request.extend_from_slice(&buf[..n]);
+        };
+
+        let head = String::from_utf8_lossy(&request[..head_len]).to_lowercase();
+        let content_length = head.lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .and_then(|length| length.trim().parse::<usize>().ok())
+            .unwrap_or(0);
+        while request.len() < head_len + content_length {
+            let n = stream.read(&mut buf).await.ok().filter(|n| *n > 0)?;
+            request.extend_from_slice(&buf[..n]);
+        }
+
+        let path = String::from_utf8_lossy(&request[..head_len]).split(' ').nth(1)?.to_string();
+        Some((stream, path))
+    }
+
+    async fn write_response(mut stream: TcpStream, status: u16, path: &str, body: &[u8]) {
+        let content_type = if path.starts_with("/img/") { "image/jpeg" } else { "text/html" };
+        let head = format!(
+            "HTTP/1.1 {} OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status, content_type, body.len(),
+        );
+
+        let _ = stream.write_all(head.as_bytes()).await;
+        let _ = stream.write_all(body).await;
+    }
+}
@@ -0,0 +1,316 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use zip::{CompressionMethod, ZipWriter, write::FileOptions};
+use crate::{
+    EhResult,
+    eh_cbz::escape_xml,
+    eh_downloader,
+    eh_url::EhUrl,
+    settings::Settings,
+    structures::GalleryDetail,
+};
+
+/// The Dublin Core metadata of an EPUB.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct EpubMetadata {
+    /// The gallery url, used as the unique identifier.
+    pub identifier: String,
+    pub title: String,
+    pub title_jpn_opt: Option<String>,
+    /// The artists, or the uploader if there is none.
+    pub creator_vec: Vec<String>,
+    pub publisher: String,
+    /// `namespace:tag`.
+    pub subject_vec: Vec<String>,
+    /// ISO 639-1, `und` if unknown.
+    pub language: String,
+    /// `2021-11-28`.
+    pub date: String,
+}
+
+impl EpubMetadata {
+    pub fn new(detail: &GalleryDetail, settings: &Settings) -> EpubMetadata {
+        let mut creator_vec = detail.tags_of(NAMESPACE_ARTIST);
+        if creator_vec.is_empty() {
+            creator_vec.push(detail.uploader.clone());
+        }
+
+        EpubMetadata {
+            identifier: EhUrl::new(settings.clone()).gallery_detail(detail.identity.gid, &detail.identity.token, false, None),
+            title: detail.title.clone(),
+            title_jpn_opt: Some(detail.title_jpn.clone()).filter(|title_jpn| !title_jpn.is_empty()),
+            creator_vec,
+            publisher: detail.uploader.clone(),
+            subject_vec: detail.tag_vec(),
+            language: detail.language_detection()
+                .and_then(|detection| detection.language.iso_code_opt())
+                .unwrap_or(LANGUAGE_UNDETERMINED)
+                .to_string(),
+            date: detail.detail.posted.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// A page of the book, as stored in the archive.
+struct EpubPage {
+    /// The downloaded image.
+    source: PathBuf,
+    /// `001`.
+    id: String,
+    image_name: String,
+    media_type: &'static str,
+    width: u32,
+    height: u32,
+}
+
+/// Pack a folder written by the `Downloader` into a fixed-layout EPUB 3 at
+/// `path`, one image per page at its own size.
+///
+/// Fails without writing anything if the folder has no image, a book
+/// needs at least one page.
+pub fn export_epub<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, metadata: &EpubMetadata, path: Q) -> EhResult<()> {
+    let mut page_vec = vec![];
+    for page_path in eh_downloader::downloaded_page_vec(dir)? {
+        let (Some(id), Some(extension)) = (
+            page_path.file_stem().and_then(|stem| stem.to_str()),
+            page_path.extension().and_then(|extension| extension.to_str()),
+        ) else {
+            continue;
+        };
+
+        let extension = extension.to_lowercase();
+        let Some(media_type) = media_type_of(&extension) else {
+            continue;
+        };
+
+        let (width, height) = image::image_dimensions(&page_path)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        page_vec.push(EpubPage {
+            id: String::from(id),
+            image_name: format!("{}.{}", id, extension),
+            source: page_path,
+            media_type,
+            width,
+            height,
+        });
+    }
+
+    if page_vec.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no image to export").into());
+    }
+
+    let path = path.as_ref();
+    let result = write_epub(&page_vec, metadata, path);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+fn write_epub(page_vec: &[EpubPage], metadata: &EpubMetadata, path: &Path) -> EhResult<()> {
+    let mut zip = ZipWriter::new(File::create(path)?);
+    let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+    let deflated = FileOptions::default();
+
+    // must be the first entry, uncompressed.
+    zip.start_file("mimetype", stored).map_err(std::io::Error::from)?;
+    zip.write_all(MIME_TYPE.as_bytes())?;
+
+    zip.start_file("META-INF/container.xml", deflated).map_err(std::io::Error::from)?;
+    zip.write_all(CONTAINER_XML.as_bytes())?;
+
+    for page in page_vec {
+        zip.start_file(format!("OEBPS/images/{}", page.image_name), stored).map_err(std::io::Error::from)?;
+        zip.write_all(&fs::read(&page.source)?)?;
+
+        zip.start_file(format!("OEBPS/pages/{}.xhtml", page.id), deflated).map_err(std::io::Error::from)?;
+        zip.write_all(page_xhtml(page, &metadata.title).as_bytes())?;
+    }
+
+    zip.start_file("OEBPS/nav.xhtml", deflated).map_err(std::io::Error::from)?;
+    zip.write_all(nav_xhtml(page_vec, &metadata.title).as_bytes())?;
+
+    zip.start_file("OEBPS/content.opf", deflated).map_err(std::io::Error::from)?;
+    zip.write_all(content_opf(page_vec, metadata).as_bytes())?;
+
+    zip.finish().map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+fn media_type_of(extension: &str) -> Option<&'static str> {
+    match extension {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn page_xhtml(page: &EpubPage, title: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head>
+<title>{title}</title>
+<meta name="viewport" content="width={width}, height={height}"/>
+<style>html, body {{ margin: 0; padding: 0; }} img {{ display: block; width: {width}px; height: {height}px; }}</style>
+</head>
+<body>
+<img src="../images/{image_name}" alt="{id}"/>
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        width = page.width,
+        height = page.height,
+        image_name = page.image_name,
+        id = page.id,
+    )
+}
+
+fn nav_xhtml(page_vec: &[EpubPage], title: &str) -> String {
+    let item_vec = page_vec.iter()
+        .map(|page| format!(r#"<li><a href="pages/{id}.xhtml">{id}</a></li>"#, id = page.id))
+        .collect::<Vec<String>>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE html>
+<html xmlns="http://www.w3.org/1999/xhtml" xmlns:epub="http://www.idpf.org/2007/ops">
+<head><title>{title}</title></head>
+<body>
+<nav epub:type="toc" id="toc">
+<h1>{title}</h1>
+<ol>
+{items}
+</ol>
+</nav>
+</body>
+</html>
+"#,
+        title = escape_xml(title),
+        items = item_vec.join("\n"),
+    )
+}
+
+fn content_opf(page_vec: &[EpubPage], metadata: &EpubMetadata) -> String {
+    let mut metadata_vec = vec![
+        format!(r#"<dc:identifier id="book-id">{}</dc:identifier>"#, escape_xml(&metadata.identifier)),
+        format!(r#"<dc:title id="title">{}</dc:title>"#, escape_xml(&metadata.title)),
+    ];
+
+    if let Some(title_jpn) = &metadata.title_jpn_opt {
+        // an alternate title, the first `dc:title` is the main one.
+        metadata_vec.push(format!(r#"<dc:title id="title-jpn" xml:lang="ja">{}</dc:title>"#, escape_xml(title_jpn)));
+    }
+
+    metadata_vec.extend(metadata.creator_vec.iter().map(|creator| format!("<dc:creator>{}</dc:creator>", escape_xml(creator))));
+    metadata_vec.push(format!("<dc:publisher>{}</dc:publisher>", escape_xml(&metadata.publisher)));
+    metadata_vec.extend(metadata.subject_vec.iter().map(|subject| format!("<dc:subject>{}</dc:subject>", escape_xml(subject))));
+    metadata_vec.push(format!("<dc:language>{}</dc:language>", escape_xml(&metadata.language)));
+    metadata_vec.push(format!("<dc:date>{}</dc:date>", escape_xml(&metadata.date)));
+    metadata_vec.push(format!(r#"<meta property="dcterms:modified">{}</meta>"#, Utc::now().format("%Y-%m-%dT%H:%M:%SZ")));
+    metadata_vec.push(String::from(r#"<meta property="rendition:layout">pre-paginated</meta>"#));
+    metadata_vec.push(String::from(r#"<meta property="rendition:spread">none</meta>"#));
+
+    let mut manifest_vec = vec![String::from(r#"<item id="nav" href="nav.xhtml" media-type="application/xhtml+xml" properties="nav"/>"#)];
+    for (index, page) in page_vec.iter().enumerate() {
+        let properties = if index == 0 { r#" properties="cover-image""# } else { "" };
+        manifest_vec.push(format!(
+            r#"<item id="image-{id}" href="images/{image_name}" media-type="{media_type}"{properties}/>"#,
+            id = page.id,
+            image_name = page.image_name,
+            media_type = page.media_type,
+            properties = properties,
+        ));
+        manifest_vec.push(format!(r#"<item id="page-{id}" href="pages/{id}.xhtml" media-type="application/xhtml+xml"/>"#, id = page.id));
+    }
+
+    let spine_vec = page_vec.iter()
+        .map(|page| format!(r#"<itemref idref="page-{}"/>"#, page.id))
+        .collect::<Vec<String>>();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0" unique-identifier="book-id" prefix="rendition: http://www.idpf.org/vocab/rendition/#">
+<metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+{metadata}
+</metadata>
+<manifest>
+{manifest}
+</manifest>
+<spine>
+{spine}
+</spine>
+</package>
+"#,
+        metadata = metadata_vec.join("\n"),
+        manifest = manifest_vec.join("\n"),
+        spine = spine_vec.join("\n"),
+    )
+}
+
+const MIME_TYPE: &str = "application/epub+zip";
+const CONTAINER_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+<rootfiles>
+<rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+</rootfiles>
+</container>
+"#;
+
+const NAMESPACE_ARTIST: &str = "artist";
+const LANGUAGE_UNDETERMINED: &str = "und";
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use super::*;
+
+    #[test]
+    fn export_epub_test() {
+        let dir = std::env::temp_dir().join(format!("eh_epub_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(4, 6).save(dir.join("001.png")).unwrap();
+        image::RgbImage::new(8, 6).save(dir.join("002.jpg")).unwrap();
+
+        let metadata = EpubMetadata {
+            title: String::from("Title & More"),
+            title_jpn_opt: Some(String::from("タイトル")),
+            language: String::from("en"),
+            ..EpubMetadata::default()
+        };
+        let path = dir.join("gallery.epub");
+        export_epub(&dir, &metadata, &path).unwrap();
+
+        let mut archive = zip::ZipArchive::new(File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.by_index(0).unwrap().name(), "mimetype");
+
+        let mut opf = String::new();
+        archive.by_name("OEBPS/content.opf").unwrap().read_to_string(&mut opf).unwrap();
+        assert!(opf.contains("<dc:title id=\"title\">Title &amp; More</dc:title>"));
+        assert!(opf.contains(r#"<dc:title id="title-jpn" xml:lang="ja">タイトル</dc:title>"#));
+        assert!(!opf.contains("refines=\"#title-jpn\""));
+        assert!(opf.contains(r#"<item id="image-002" href="images/002.jpg" media-type="image/jpeg"/>"#));
+        assert!(opf.contains(r#"<itemref idref="page-001"/>"#));
+
+        let mut page = String::new();
+        archive.by_name("OEBPS/pages/002.xhtml").unwrap().read_to_string(&mut page).unwrap();
+        assert!(page.contains(r#"content="width=8, height=6""#));
+
+        let empty_dir = dir.join("empty");
+        fs::create_dir_all(&empty_dir).unwrap();
+        fs::write(empty_dir.join("001.txt"), b"").unwrap();
+        let path = dir.join("empty.epub");
+        assert!(export_epub(&empty_dir, &metadata, &path).is_err());
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use flate2::{Compression, write::ZlibEncoder};
+use crate::{
+    EhResult,
+    eh_downloader,
+    structures::GalleryDetail,
+};
+
+/// The document information dictionary of a PDF.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct PdfInfo {
+    pub title: String,
+    /// The uploader.
+    pub author: String,
+    /// `namespace:tag`, comma separated.
+    pub keywords: String,
+    /// `D:20211128101200Z`.
+    pub creation_date: String,
+}
+
+impl PdfInfo {
+    pub fn new(detail: &GalleryDetail) -> PdfInfo {
+        PdfInfo {
+            title: detail.title.clone(),
+            author: detail.uploader.clone(),
+            keywords: detail.tag_vec().join(", "),
+            creation_date: detail.detail.posted.format("D:%Y%m%d%H%M%SZ").to_string(),
+        }
+    }
+}
+
+/// An image as drawn on a page.
+struct PdfImage {
+    width: u32,
+    height: u32,
+    color_space: &'static str,
+    /// `DCTDecode` for a JPEG embedded as is, `FlateDecode` for decoded pixels.
+    filter: &'static str,
+    data: Vec<u8>,
+}
+
+/// Write a folder written by the `Downloader` as a PDF at `path`, one page
+/// per image, one point per pixel.
+///
+/// JPEGs are embedded as they are, other images are decoded, put on white
+/// if they have transparency, and compressed again.
+///
+/// Fails without writing anything if the folder has no page, and removes
+/// the file if a page is not an image.
+pub fn export_pdf<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, info: &PdfInfo, path: Q) -> EhResult<()> {
+    let page_path_vec = eh_downloader::downloaded_page_vec(dir)?;
+    if page_path_vec.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no page to export").into());
+    }
+
+    let path = path.as_ref();
+    let result = write_pdf(&page_path_vec, info, path);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+fn write_pdf(page_path_vec: &[PathBuf], info: &PdfInfo, path: &Path) -> EhResult<()> {
+    let mut writer = PdfWriter::new(BufWriter::new(File::create(path)?))?;
+
+    // 1 is the catalog, 2 the page tree, 3 the info, then 3 objects per page.
+    let page_id_vec = (0..page_path_vec.len())
+        .map(|index| FIRST_PAGE_ID + OBJECTS_PER_PAGE * index)
+        .collect::<Vec<usize>>();
+
+    for (page_path, page_id) in page_path_vec.iter().zip(&page_id_vec) {
+        let image = read_image(&fs::read(page_path)?)?;
+        let (content_id, image_id) = (page_id + 1, page_id + 2);
+
+        writer.object(*page_id, format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>",
+            PAGES_ID, image.width, image.height, image_id, content_id,
+        ).as_bytes())?;
+
+        let content = format!("q {} 0 0 {} 0 0 cm /Im0 Do Q", image.width, image.height);
+        writer.stream(content_id, &format!("<< /Length {} >>", content.len()), content.as_bytes())?;
+
+        let dictionary = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8 /Filter /{} /Length {} >>",
+            image.width, image.height, image.color_space, image.filter, image.data.len(),
+        );
+        writer.stream(image_id, &dictionary, &image.data)?;
+    }
+
+    writer.object(CATALOG_ID, format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES_ID).as_bytes())?;
+
+    let kids = page_id_vec.iter().map(|id| format!("{} 0 R", id)).collect::<Vec<String>>().join(" ");
+    writer.object(PAGES_ID, format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids, page_id_vec.len()).as_bytes())?;
+
+    // dates are plain ASCII, not text strings.
+    let creation_date = if info.creation_date.is_empty() {
+        String::new()
+    } else {
+        format!(" /CreationDate {}", literal_string(&info.creation_date))
+    };
+
+    writer.object(INFO_ID, format!(
+        "<< /Title {} /Author {} /Keywords {}{} /Producer {} >>",
+        text_string(&info.title),
+        text_string(&info.author),
+        text_string(&info.keywords),
+        creation_date,
+        text_string(PRODUCER),
+    ).as_bytes())?;
+
+    writer.finish()
+}
+
+/// Objects are written in any order, their offsets are collected for the
+/// cross-reference table.
+struct PdfWriter<W: Write> {
+    out: W,
+    position: usize,
+    /// By object id, 0 is the free head of the table.
+    offset_vec: Vec<usize>,
+}
+
+impl<W: Write> PdfWriter<W> {
+    fn new(out: W) -> EhResult<PdfWriter<W>> {
+        let mut writer = PdfWriter { out, position: 0, offset_vec: vec![0] };
+        // the binary comment tells readers the file is not plain text.
+        writer.write(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+
+        Ok(writer)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> EhResult<()> {
+        self.out.write_all(bytes)?;
+        self.position += bytes.len();
+        Ok(())
+    }
+
+    fn begin(&mut self, id: usize) -> EhResult<()> {
+        if self.offset_vec.len() <= id {
+            self.offset_vec.resize(id + 1, 0);
+        }
+
+        self.offset_vec[id] = self.position;
+        self.write(format!("{} 0 obj\n", id).as_bytes())
+    }
+
+    fn object(&mut self, id: usize, body: &[u8]) -> EhResult<()> {
+        self.begin(id)?;
+        self.write(body)?;
+        self.write(b"\nendobj\n")
+    }
+
+    fn stream(&mut self, id: usize, dictionary: &str, data: &[u8]) -> EhResult<()> {
+        self.begin(id)?;
+        self.write(dictionary.as_bytes())?;
+        self.write(b"\nstream\n")?;
+        self.write(data)?;
+        self.write(b"\nendstream\nendobj\n")
+    }
+
+    fn finish(mut self) -> EhResult<()> {
+        let xref_position = self.position;
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offset_vec.len());
+        for offset in &self.offset_vec[1..] {
+            xref.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offset_vec.len(), CATALOG_ID, INFO_ID, xref_position,
+        ));
+        self.write(xref.as_bytes())?;
+        self.out.flush()?;
+
+        Ok(())
+    }
+}
+
+fn read_image(bytes: &[u8]) -> EhResult<PdfImage> {
+    if let Some((width, height, components)) = jpeg_frame(bytes) {
+        let color_space_opt = match components {
+            1 => Some("DeviceGray"),
+            3 => Some("DeviceRGB"),
+            _ => None,
+        };
+
+        if let Some(color_space) = color_space_opt {
+            return Ok(PdfImage { width, height, color_space, filter: "DCTDecode", data: bytes.to_vec() });
+        }
+    }
+
+    let image = image::load_from_memory(bytes)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    let rgba = image.to_rgba8();
+    let (width, height) = rgba.dimensions();
+
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for pixel in rgba.pixels() {
+        let [r, g, b, a] = pixel.0;
+        encoder.write_all(&[on_white(r, a), on_white(g, a), on_white(b, a)])?;
+    }
+
+    Ok(PdfImage { width, height, color_space: "DeviceRGB", filter: "FlateDecode", data: encoder.finish()? })
+}
+
+/// Blend a channel of a pixel with opacity `alpha` over white.
+fn on_white(channel: u8, alpha: u8) -> u8 {
+    let (channel, alpha) = (channel as u32, alpha as u32);
+    ((channel * alpha + 255 * (255 - alpha)) / 255) as u8
+}
+
+/// The width, height and component count of a JPEG, from its start of frame.
+fn jpeg_frame(bytes: &[u8]) -> Option<(u32, u32, u8)> {
+    if !bytes.starts_with(&[0xFF, 0xD8]) {
+        return None;
+    }
+
+    let read_u16 = |index: usize| -> Option<u32> {
+        Some(u16::from_be_bytes([*bytes.get(index)?, *bytes.get(index + 1)?]) as u32)
+    };
+
+    let mut index = 2;
+    while index + 4 <= bytes.len() {
+        if bytes[index] != 0xFF {
+            return None;
+        }
+
+        let marker = bytes[index + 1];
+        // every SOFn but DHT, JPG and DAC.
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let height = read_u16(index + 5)?;
+            let width = read_u16(index + 7)?;
+            let components = *bytes.get(index + 9)?;
+            return Some((width, height, components));
+        }
+
+        index += 2 + read_u16(index + 2)? as usize;
+    }
+
+    None
+}
+
+/// A text string in UTF-16BE, the only encoding readers agree on for
+/// anything but ASCII.
+fn text_string(s: &str) -> String {
+    let hex = s.encode_utf16()
+        .map(|unit| format!("{:04X}", unit))
+        .collect::<String>();
+
+    format!("<FEFF{}>", hex)
+}
+
+/// An ASCII string, with the delimiters escaped.
+fn literal_string(s: &str) -> String {
+    let escaped = s.replace('\\', "\\\\")
+        .replace('(', "\\(")
+        .replace(')', "\\)");
+
+    format!("({})", escaped)
+}
+
+const CATALOG_ID: usize = 1;
+const PAGES_ID: usize = 2;
+const INFO_ID: usize = 3;
+const FIRST_PAGE_ID: usize = 4;
+const OBJECTS_PER_PAGE: usize = 3;
+const PRODUCER: &str = "ehviewer-x";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_pdf_test() {
+        let dir = std::env::temp_dir().join(format!("eh_pdf_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        image::RgbImage::new(8, 6).save(dir.join("001.jpg")).unwrap();
+        image::RgbaImage::new(4, 6).save(dir.join("002.png")).unwrap();
+
+        let jpeg = fs::read(dir.join("001.jpg")).unwrap();
+        assert_eq!(jpeg_frame(&jpeg), Some((8, 6, 3)));
+
+        let info = PdfInfo {
+            title: String::from("タイトル"),
+            creation_date: String::from("D:20211128101200Z"),
+            ..PdfInfo::default()
+        };
+        let path = dir.join("gallery.pdf");
+        export_pdf(&dir, &info, &path).unwrap();
+
+        let pdf = String::from_utf8_lossy(&fs::read(&path).unwrap()).to_string();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/Type /Pages /Kids [4 0 R 7 0 R] /Count 2"));
+        assert!(pdf.contains("/MediaBox [0 0 8 6]"));
+        assert!(pdf.contains("/Filter /DCTDecode"));
+        assert!(pdf.contains("/Filter /FlateDecode"));
+        assert!(pdf.contains("/Title <FEFF30BF30A430C830EB>"));
+        assert!(pdf.contains("/CreationDate (D:20211128101200Z)"));
+        assert!(pdf.ends_with("%%EOF\n"));
+
+        let empty_dir = dir.join("empty");
+        fs::create_dir_all(&empty_dir).unwrap();
+        let path = dir.join("empty.pdf");
+        assert!(export_pdf(&empty_dir, &info, &path).is_err());
+        assert!(!path.exists());
+
+        fs::write(empty_dir.join("001.txt"), b"text").unwrap();
+        assert!(export_pdf(&empty_dir, &info, &path).is_err());
+        assert!(!path.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn on_white_test() {
+        assert_eq!(on_white(0, 0), 255);
+        assert_eq!(on_white(10, 255), 10);
+    }
+}
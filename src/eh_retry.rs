@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use rand::Rng;
+use crate::eh_client::Method;
+
+/// Which failures are worth another attempt.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum RetryCondition {
+    /// The connection could not be established.
+    Connect,
+    Timeout,
+    /// A 5xx status.
+    ServerError,
+    /// An html page without `</html>`, the connection dropped midway.
+    TruncatedHtml,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryRule {
+    /// Including the first one, 1 disables retrying.
+    pub max_attempts: u32,
+    /// The delay after the first failure, doubled after each further one.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Fraction of the delay that is randomized, from 0.0 to 1.0.
+    pub jitter: f64,
+    pub condition_vec: Vec<RetryCondition>,
+}
+
+impl Default for RetryRule {
+    fn default() -> Self {
+        RetryRule {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter: 0.5,
+            condition_vec: vec![
+                RetryCondition::Connect,
+                RetryCondition::Timeout,
+                RetryCondition::ServerError,
+                RetryCondition::TruncatedHtml,
+            ],
+        }
+    }
+}
+
+impl RetryRule {
+    /// A rule that never retries.
+    pub fn none() -> RetryRule {
+        RetryRule { max_attempts: 1, ..RetryRule::default() }
+    }
+
+    /// Whether `attempt` (starting at 1) failed with `condition` may be followed by another.
+    pub fn should_retry(&self, attempt: u32, condition: RetryCondition) -> bool {
+        attempt < self.max_attempts && self.condition_vec.contains(&condition)
+    }
+
+    /// How long to wait after `attempt` (starting at 1) failed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(31);
+        let delay = self.base_delay.saturating_mul(1 << exp).min(self.max_delay);
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+
+        let factor = 1.0 - jitter * rand::thread_rng().gen::<f64>();
+        delay.mul_f64(factor)
+    }
+}
+
+/// A default `RetryRule` with per-`Method` overrides.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RetryPolicy {
+    pub default_rule: RetryRule,
+    override_map: HashMap<Method, RetryRule>,
+}
+
+impl Default for RetryPolicy {
+    /// Signing in is never retried, posting credentials again only
+    /// brings the account closer to the attempt limit.
+    fn default() -> Self {
+        let mut policy = RetryPolicy::new(RetryRule::default());
+        policy.set_override(Method::MethodSignIn, RetryRule::none());
+        policy
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(default_rule: RetryRule) -> RetryPolicy {
+        RetryPolicy { default_rule, override_map: HashMap::new() }
+    }
+
+    /// Never retry anything.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::new(RetryRule::none())
+    }
+
+    pub fn set_override(&mut self, method: Method, rule: RetryRule) {
+        self.override_map.insert(method, rule);
+    }
+
+    pub fn remove_override(&mut self, method: Method) {
+        self.override_map.remove(&method);
+    }
+
+    pub fn rule(&self, method: Method) -> &RetryRule {
+        self.override_map.get(&method).unwrap_or(&self.default_rule)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_test() {
+        let rule = RetryRule { jitter: 0.0, ..RetryRule::default() };
+        assert_eq!(rule.delay(1), Duration::from_millis(500));
+        assert_eq!(rule.delay(2), Duration::from_secs(1));
+        assert_eq!(rule.delay(3), Duration::from_secs(2));
+        assert_eq!(rule.delay(10), Duration::from_secs(8));
+
+        let rule = RetryRule::default();
+        for attempt in 1..5 {
+            let delay = rule.delay(attempt);
+            let max = RetryRule { jitter: 0.0, ..RetryRule::default() }.delay(attempt);
+            assert!(delay <= max && delay >= max / 2);
+        }
+    }
+
+    #[test]
+    fn should_retry_test() {
+        let rule = RetryRule::default();
+        assert!(rule.should_retry(1, RetryCondition::ServerError));
+        assert!(rule.should_retry(2, RetryCondition::TruncatedHtml));
+        assert!(!rule.should_retry(3, RetryCondition::Connect));
+
+        let rule = RetryRule { condition_vec: vec![RetryCondition::Timeout], ..RetryRule::default() };
+        assert!(!rule.should_retry(1, RetryCondition::ServerError));
+    }
+
+    #[test]
+    fn override_test() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.rule(Method::MethodSignIn).max_attempts, 1);
+        assert_eq!(policy.rule(Method::MethodGetGalleryPage), &RetryRule::default());
+    }
+}
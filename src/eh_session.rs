@@ -0,0 +1,495 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::RwLock;
+use chrono::Utc;
+use cookie::Cookie;
+use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+use serde::{Deserialize, Serialize};
+use crate::{EhResult, eh_config, eh_url};
+
+/// A cookie jar that knows which E-Hentai cookies belong to which site
+/// and can be written to and restored from a file.
+///
+/// It is handed to `reqwest` as the cookie provider, so every `Set-Cookie`
+/// coming back from the server lands here.
+#[derive(Debug, Default)]
+pub struct EhSession {
+    cookie_vec: RwLock<Vec<SessionCookie>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionCookie {
+    pub name: String,
+    pub value: String,
+    /// Without the leading dot.
+    pub domain: String,
+    /// `true` if the cookie is only sent to `domain` itself, not to its subdomains.
+    pub host_only: bool,
+    /// Only sent to this path and below it.
+    #[serde(default = "root_path")]
+    pub path: String,
+    /// Unix timestamp, `None` for a session cookie.
+    pub expires_opt: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionFile {
+    cookie_vec: Vec<SessionCookie>,
+}
+
+impl EhSession {
+    pub fn new() -> EhSession {
+        EhSession::default()
+    }
+
+    /// Restore a session previously written by `save`.
+    pub fn load<P: AsRef<Path>>(path: P) -> EhResult<EhSession> {
+        let s = fs::read_to_string(path)?;
+        let file = serde_json::from_str::<SessionFile>(&s).map_err(crate::parser::ParseError::from)?;
+
+        let session = EhSession::new();
+        for cookie in file.cookie_vec {
+            session.insert(cookie);
+        }
+
+        Ok(session)
+    }
+
+    /// Same as `load`, but a missing file yields an empty session.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> EhResult<EhSession> {
+        if path.as_ref().exists() {
+            EhSession::load(path)
+        } else {
+            Ok(EhSession::new())
+        }
+    }
+
+    /// Write the persistent cookies (see `PERSISTENT_COOKIES`) to `path`.
+    ///
+    /// They are login credentials, on Unix the file is only readable by its owner.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> EhResult<()> {
+        let now = Utc::now().timestamp();
+        let cookie_vec = self.cookie_vec.read().unwrap()
+            .iter()
+            .filter(|c| PERSISTENT_COOKIES.contains(&c.name.as_str()))
+            .filter(|c| !c.is_expired(now))
+            .cloned()
+            .collect::<Vec<SessionCookie>>();
+
+        let s = serde_json::to_string_pretty(&SessionFile { cookie_vec })
+            .map_err(crate::parser::ParseError::from)?;
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(SESSION_FILE_MODE);
+        }
+
+        let mut file = options.open(path)?;
+        // the mode above only applies to a new file.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(SESSION_FILE_MODE))?;
+        }
+
+        file.write_all(s.as_bytes())?;
+        Ok(())
+    }
+
+    /// Set a cookie for `domain` and all of its subdomains.
+    pub fn set_cookie(&self, domain: &str, name: &str, value: &str) {
+        self.insert(SessionCookie {
+            name: String::from(name),
+            value: String::from(value),
+            domain: String::from(domain.trim_start_matches('.')),
+            host_only: false,
+            path: root_path(),
+            expires_opt: None,
+        });
+    }
+
+    /// Returns the value of the cookie `name` that would be sent to `host`.
+    pub fn get_cookie(&self, host: &str, name: &str) -> Option<String> {
+        let now = Utc::now().timestamp();
+        self.cookie_vec.read().unwrap()
+            .iter()
+            .find(|c| c.name == name && c.matches(host) && !c.is_expired(now))
+            .map(|c| c.value.clone())
+    }
+
+    pub fn remove_cookie(&self, domain: &str, name: &str) {
+        let domain = domain.trim_start_matches('.');
+        self.cookie_vec.write().unwrap().retain(|c| !(c.name == name && c.domain == domain));
+    }
+
+    pub fn clear(&self) {
+        self.cookie_vec.write().unwrap().clear();
+    }
+
+    /// A copy of every cookie currently held.
+    pub fn cookies_snapshot(&self) -> Vec<SessionCookie> {
+        self.cookie_vec.read().unwrap().clone()
+    }
+
+    /// Both member cookies are present for e-hentai.org.
+    pub fn is_signed_in(&self) -> bool {
+        self.member_id_opt().is_some()
+            && self.get_cookie(eh_url::DOMAIN_E, KEY_IPB_PASS_HASH).is_some()
+    }
+
+    pub fn member_id_opt(&self) -> Option<u64> {
+        self.get_cookie(eh_url::DOMAIN_E, KEY_IPB_MEMBER_ID)
+            .and_then(|id| id.parse::<u64>().ok())
+    }
+
+    /// Set the uconfig cookie, for both sites.
+    pub fn set_uconfig(&self, uconfig: &str) {
+        self.set_cookie(eh_url::DOMAIN_E, eh_config::KEY_UCONFIG, uconfig);
+        self.set_cookie(eh_url::DOMAIN_EX, eh_config::KEY_UCONFIG, uconfig);
+    }
+
+    /// exhentai.org accepts the same member cookies as e-hentai.org,
+    /// copy them over if they are missing or outdated.
+    pub fn copy_member_cookies_to_exhentai(&self) {
+        for name in [KEY_IPB_MEMBER_ID, KEY_IPB_PASS_HASH] {
+            let Some(value) = self.get_cookie(eh_url::DOMAIN_E, name) else {
+                continue;
+            };
+
+            if self.get_cookie(eh_url::DOMAIN_EX, name).as_deref() != Some(value.as_str()) {
+                self.set_cookie(eh_url::DOMAIN_EX, name, &value);
+            }
+        }
+    }
+
+    /// Import a Netscape `cookies.txt`, as exported by most browser extensions.
+    ///
+    /// Fields are separated by tabs:
+    /// ```text
+    /// # Netscape HTTP Cookie File
+    /// .e-hentai.org  TRUE  /  FALSE  1708012345  ipb_member_id  1234567
+    /// #HttpOnly_.exhentai.org  TRUE  /  FALSE  1708012345  igneous  0123456789
+    /// ```
+    /// Lines for other sites are skipped. Returns the number of cookies imported.
+    pub fn import_cookies_txt(&self, s: &str) -> usize {
+        let mut count = 0;
+        for line in s.lines() {
+            let line = line.trim();
+            let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<&str>>();
+            if fields.len() != 7 {
+                continue;
+            }
+
+            let domain = fields[0].trim_start_matches('.');
+            if !is_eh_domain(domain) {
+                continue;
+            }
+
+            let host_only = !fields[1].eq_ignore_ascii_case("TRUE") && !fields[0].starts_with('.');
+            let expires_opt = match fields[4].parse::<i64>() {
+                Ok(0) | Err(_) => None,
+                Ok(expires) => Some(expires),
+            };
+
+            self.insert(SessionCookie {
+                name: String::from(fields[5]),
+                value: String::from(fields[6]),
+                domain: String::from(domain),
+                host_only,
+                path: String::from(fields[2]),
+                expires_opt,
+            });
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Import a raw `Cookie` header value, such as `ipb_member_id=1; ipb_pass_hash=abc`.
+    ///
+    /// `igneous` only belongs to exhentai.org, everything else is set for
+    /// both e-hentai.org (which covers the forums) and exhentai.org.
+    /// Returns the number of cookies imported.
+    pub fn import_cookie_header(&self, header: &str) -> usize {
+        let mut count = 0;
+        for pair in header.split(';') {
+            let Some((name, value)) = pair.split_once('=') else {
+                continue;
+            };
+
+            let (name, value) = (name.trim(), value.trim());
+            if name.is_empty() {
+                continue;
+            }
+
+            for domain in scope_of(name) {
+                self.set_cookie(domain, name, value);
+            }
+            count += 1;
+        }
+
+        count
+    }
+
+    fn insert(&self, cookie: SessionCookie) {
+        let mut cookie_vec = self.cookie_vec.write().unwrap();
+        cookie_vec.retain(|c| !(c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path));
+
+        if !cookie.is_expired(Utc::now().timestamp()) && cookie.value != DELETED {
+            cookie_vec.push(cookie);
+        }
+    }
+}
+
+impl CookieStore for EhSession {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item=&HeaderValue>, url: &Url) {
+        let Some(host) = url.host_str() else {
+            return;
+        };
+
+        let now = Utc::now().timestamp();
+        for header in cookie_headers {
+            let Ok(header) = header.to_str() else {
+                continue;
+            };
+            let Ok(cookie) = Cookie::parse(header) else {
+                continue;
+            };
+
+            // a server can only set cookies for itself and its parent domains.
+            let (domain, host_only) = match cookie.domain() {
+                Some(domain) => {
+                    let domain = domain.trim_start_matches('.');
+                    if !domain_matches(host, domain) {
+                        continue;
+                    }
+
+                    (domain, false)
+                }
+                None => (host, true),
+            };
+
+            let path = match cookie.path() {
+                Some(path) if path.starts_with('/') => String::from(path),
+                _ => default_path_of(url),
+            };
+
+            // Max-Age takes precedence over Expires.
+            let expires_opt = if let Some(max_age) = cookie.max_age() {
+                Some(now + max_age.whole_seconds())
+            } else {
+                cookie.expires_datetime().map(|e| e.unix_timestamp())
+            };
+
+            self.insert(SessionCookie {
+                name: String::from(cookie.name()),
+                value: String::from(cookie.value()),
+                domain: String::from(domain),
+                host_only,
+                path,
+                expires_opt,
+            });
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let host = url.host_str()?;
+        let now = Utc::now().timestamp();
+
+        let s = self.cookie_vec.read().unwrap()
+            .iter()
+            .filter(|c| c.matches(host) && c.matches_path(url.path()) && !c.is_expired(now))
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        if s.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&s).ok()
+        }
+    }
+}
+
+impl SessionCookie {
+    fn matches(&self, host: &str) -> bool {
+        if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(host, &self.domain)
+        }
+    }
+
+    /// RFC 6265 5.1.4, `/a` covers `/a` and `/a/b` but not `/ab`.
+    fn matches_path(&self, path: &str) -> bool {
+        path == self.path
+            || path.starts_with(&self.path) && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/'))
+    }
+
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expires_opt, Some(expires) if expires <= now)
+    }
+}
+
+/// `host` is `domain` or one of its subdomains.
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// RFC 6265 5.1.4, the directory of the request path.
+fn default_path_of(url: &Url) -> String {
+    match url.path().rfind('/') {
+        Some(index) if index > 0 => String::from(&url.path()[..index]),
+        _ => root_path(),
+    }
+}
+
+fn root_path() -> String {
+    String::from("/")
+}
+
+fn is_eh_domain(domain: &str) -> bool {
+    [eh_url::DOMAIN_E, eh_url::DOMAIN_EX].iter()
+        .any(|d| domain == *d || domain.ends_with(&format!(".{}", d)))
+}
+
+fn scope_of(name: &str) -> &'static [&'static str] {
+    if name == KEY_IGNEOUS {
+        &[eh_url::DOMAIN_EX]
+    } else {
+        &[eh_url::DOMAIN_E, eh_url::DOMAIN_EX]
+    }
+}
+
+pub const KEY_IPB_MEMBER_ID: &str = "ipb_member_id";
+pub const KEY_IPB_PASS_HASH: &str = "ipb_pass_hash";
+pub const KEY_IGNEOUS: &str = "igneous";
+pub const KEY_SK: &str = "sk";
+
+/// The `igneous` value handed out to accounts without exhentai access.
+pub const IGNEOUS_MYSTERY: &str = "mystery";
+
+/// Cookies written by `EhSession::save`, everything else lives for the process only.
+const PERSISTENT_COOKIES: [&str; 5] = [
+    KEY_IPB_MEMBER_ID,
+    KEY_IPB_PASS_HASH,
+    KEY_IGNEOUS,
+    KEY_SK,
+    eh_config::KEY_UCONFIG,
+];
+
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+#[cfg(unix)]
+const SESSION_FILE_MODE: u32 = 0o600;
+const DELETED: &str = "deleted";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn import_cookies_txt_test() {
+        let s = "# Netscape HTTP Cookie File\n\
+                 .e-hentai.org\tTRUE\t/\tFALSE\t0\tipb_member_id\t1234567\n\
+                 .e-hentai.org\tTRUE\t/\tFALSE\t0\tipb_pass_hash\t0123456789abcdef\n\
+                 #HttpOnly_.exhentai.org\tTRUE\t/\tFALSE\t0\tigneous\tmystery\n\
+                 .example.com\tTRUE\t/\tFALSE\t0\tfoo\tbar\n";
+
+        let session = EhSession::new();
+        assert_eq!(session.import_cookies_txt(s), 3);
+        assert!(session.is_signed_in());
+        assert_eq!(session.member_id_opt(), Some(1234567));
+        assert_eq!(session.get_cookie("forums.e-hentai.org", KEY_IPB_MEMBER_ID), Some(String::from("1234567")));
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_EX, KEY_IGNEOUS), Some(String::from("mystery")));
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_E, KEY_IGNEOUS), None);
+    }
+
+    #[test]
+    fn import_cookie_header_test() {
+        let session = EhSession::new();
+        assert_eq!(session.import_cookie_header("ipb_member_id=1; ipb_pass_hash=abc; igneous=xyz"), 3);
+
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_EX, KEY_IPB_MEMBER_ID), Some(String::from("1")));
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_E, KEY_IPB_PASS_HASH), Some(String::from("abc")));
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_E, KEY_IGNEOUS), None);
+    }
+
+    #[test]
+    fn cookie_store_test() {
+        let session = EhSession::new();
+        let url = "https://forums.e-hentai.org/index.php".parse::<Url>().unwrap();
+        let headers = [
+            HeaderValue::from_static("ipb_member_id=1; Domain=.e-hentai.org; Path=/"),
+            HeaderValue::from_static("ipb_session_id=s; Path=/"),
+        ];
+        session.set_cookies(&mut headers.iter(), &url);
+
+        let url = "https://e-hentai.org/".parse::<Url>().unwrap();
+        assert_eq!(session.cookies(&url), Some(HeaderValue::from_static("ipb_member_id=1")));
+
+        let url = "https://forums.e-hentai.org/".parse::<Url>().unwrap();
+        assert_eq!(session.cookies(&url), Some(HeaderValue::from_static("ipb_member_id=1; ipb_session_id=s")));
+
+        // not the host nor a parent of it.
+        let headers = [
+            HeaderValue::from_static("igneous=x; Domain=.exhentai.org; Path=/"),
+            HeaderValue::from_static("foo=bar; Domain=example.com"),
+            HeaderValue::from_static("api=1; Path=/api"),
+        ];
+        session.set_cookies(&mut headers.iter(), &url);
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_EX, KEY_IGNEOUS), None);
+        assert_eq!(session.get_cookie("example.com", "foo"), None);
+
+        let url = "https://forums.e-hentai.org/api/x".parse::<Url>().unwrap();
+        assert_eq!(session.cookies(&url), Some(HeaderValue::from_static("ipb_member_id=1; ipb_session_id=s; api=1")));
+        let url = "https://forums.e-hentai.org/apix".parse::<Url>().unwrap();
+        assert_eq!(session.cookies(&url), Some(HeaderValue::from_static("ipb_member_id=1; ipb_session_id=s")));
+
+        // deleted by server.
+        let headers = [HeaderValue::from_static("ipb_member_id=deleted; Domain=.e-hentai.org; Max-Age=0")];
+        session.set_cookies(&mut headers.iter(), &url);
+        assert_eq!(session.member_id_opt(), None);
+    }
+
+    #[test]
+    fn copy_member_cookies_to_exhentai_test() {
+        let session = EhSession::new();
+        session.set_cookie(eh_url::DOMAIN_E, KEY_IPB_MEMBER_ID, "1");
+        session.set_cookie(eh_url::DOMAIN_E, KEY_IPB_PASS_HASH, "abc");
+        session.set_cookie(eh_url::DOMAIN_EX, KEY_IPB_PASS_HASH, "old");
+
+        session.copy_member_cookies_to_exhentai();
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_EX, KEY_IPB_MEMBER_ID), Some(String::from("1")));
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_EX, KEY_IPB_PASS_HASH), Some(String::from("abc")));
+    }
+
+    #[test]
+    fn save_load_test() {
+        let session = EhSession::new();
+        session.import_cookie_header("ipb_member_id=1; ipb_pass_hash=abc; foo=bar");
+
+        let path = std::env::temp_dir().join(format!("eh_session_{}.json", std::process::id()));
+        session.save(&path).unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, SESSION_FILE_MODE);
+        }
+
+        let session = EhSession::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(session.is_signed_in());
+        assert_eq!(session.get_cookie(eh_url::DOMAIN_E, "foo"), None);
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use crate::structures::{GalleryDetail, GalleryInfo, GalleryList, GalleryTagGroup, ParsedTitle};
+
+/// What a similar gallery has in common with the source one.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SimilarityMode {
+    /// Other translations and versions, searched by the core title.
+    Title,
+    /// More from the same artists or groups.
+    Artist,
+    /// More of the same parodies or characters.
+    Parody,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SimilarGallery {
+    /// From 0 to 1, higher is closer to the source gallery.
+    pub score: f64,
+    pub gallery_info: GalleryInfo,
+}
+
+/// Build the search query of `mode`, `None` if the source gallery
+/// has nothing to search with, such as no artist tag.
+pub fn similar_query(source: &GalleryDetail, mode: SimilarityMode) -> Option<String> {
+    query_of(&source.parsed_title(), &source.tag_group_vec, mode)
+}
+
+fn query_of(parsed_title: &ParsedTitle, tag_group_vec: &[GalleryTagGroup], mode: SimilarityMode) -> Option<String> {
+    let namespace_vec: &[&str] = match mode {
+        SimilarityMode::Title => {
+            // the same form `rank_similar` compares, without punctuation or quotes.
+            let title = parsed_title.normalized_title();
+            return if title.is_empty() { None } else { Some(format!("\"{}\"", title)) };
+        }
+        SimilarityMode::Artist => &["artist", "group"],
+        SimilarityMode::Parody => &["parody", "character"],
+    };
+
+    // `~` makes every term optional, any of them is a match.
+    let term_vec = tag_group_vec.iter()
+        .filter(|group| namespace_vec.contains(&group.tag_group_name.as_str()))
+        .flat_map(|group| group.tag_vec.iter().map(move |tag| (&group.tag_group_name, tag)))
+        .filter(|(_, tag)| tag.as_str() != IGNORED_PARODY)
+        .map(|(namespace, tag)| format!("~{}:\"{}$\"", namespace, tag))
+        .collect::<Vec<String>>();
+
+    if term_vec.is_empty() {
+        None
+    } else {
+        Some(term_vec.join(" "))
+    }
+}
+
+impl GalleryList {
+    /// Rank the galleries by title similarity and shared tags with `source`,
+    /// closest first. `source` itself is left out.
+    ///
+    /// Tags only count in the Compact and Extended modes, which show them.
+    pub fn rank_similar(self, source: &GalleryDetail) -> Vec<SimilarGallery> {
+        rank(self.gallery_info_vec, source.identity.gid, &source.parsed_title(), &source.tag_vec())
+    }
+}
+
+fn rank(gallery_info_vec: Vec<GalleryInfo>, source_gid: u64, source_title: &ParsedTitle, source_tag_vec: &[String]) -> Vec<SimilarGallery> {
+    let source_title = source_title.normalized_title();
+    let source_tag_set = source_tag_vec.iter().cloned().collect::<HashSet<String>>();
+
+    let mut similar_vec = gallery_info_vec.into_iter()
+        .filter(|gallery_info| gallery_info.identity.gid != source_gid)
+        .map(|gallery_info| {
+            let title = gallery_info.title.parse::<ParsedTitle>().unwrap_or_default().normalized_title();
+            let title_score = dice_coefficient(&source_title, &title);
+
+            let score = match &gallery_info.simple_tag_vec_opt {
+                Some(tag_vec) => {
+                    let tag_set = tag_vec.iter().cloned().collect::<HashSet<String>>();
+                    let tag_score = jaccard_index(&source_tag_set, &tag_set);
+                    TITLE_WEIGHT * title_score + (1.0 - TITLE_WEIGHT) * tag_score
+                }
+                None => title_score,
+            };
+
+            SimilarGallery { score, gallery_info }
+        })
+        .collect::<Vec<SimilarGallery>>();
+
+    similar_vec.sort_by(|a, b| b.score.total_cmp(&a.score)
+        .then(a.gallery_info.identity.gid.cmp(&b.gallery_info.identity.gid)));
+    similar_vec
+}
+
+/// Over the character bigrams, spaces left out, which also works for
+/// titles without spaces such as Japanese ones.
+fn dice_coefficient(a: &str, b: &str) -> f64 {
+    let bigrams = |s: &str| {
+        let char_vec = s.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+        char_vec.windows(2).map(|w| (w[0], w[1])).collect::<Vec<(char, char)>>()
+    };
+
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let mut b_rest = b.clone();
+    let mut shared = 0;
+    for bigram in &a {
+        if let Some(idx) = b_rest.iter().position(|x| x == bigram) {
+            b_rest.swap_remove(idx);
+            shared += 1;
+        }
+    }
+
+    2.0 * shared as f64 / (a.len() + b.len()) as f64
+}
+
+fn jaccard_index(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / union as f64
+}
+
+const TITLE_WEIGHT: f64 = 0.6;
+// Not a parody, searching it finds everything.
+const IGNORED_PARODY: &str = "original";
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::GalleryIdentity;
+    use super::*;
+
+    fn tag_group(namespace: &str, tag_vec: &[&str]) -> GalleryTagGroup {
+        GalleryTagGroup {
+            tag_group_name: String::from(namespace),
+            tag_vec: tag_vec.iter().map(|tag| String::from(*tag)).collect(),
+        }
+    }
+
+    fn info(gid: u64, title: &str, tag_vec_opt: Option<&[&str]>) -> GalleryInfo {
+        GalleryInfo {
+            identity: GalleryIdentity { gid, token: String::new() },
+            title: String::from(title),
+            simple_tag_vec_opt: tag_vec_opt.map(|tag_vec| tag_vec.iter().map(|tag| String::from(*tag)).collect()),
+            ..GalleryInfo::default()
+        }
+    }
+
+    #[test]
+    fn query_of_test() {
+        let parsed_title = "(C99) [Circle (Artist)] Some \"Title\"! [English]".parse::<ParsedTitle>().unwrap();
+        let tag_group_vec = [
+            tag_group("parody", &["original", "touhou project"]),
+            tag_group("character", &["reimu hakurei"]),
+            tag_group("artist", &["artist a"]),
+            tag_group("female", &["glasses"]),
+        ];
+
+        assert_eq!(query_of(&parsed_title, &tag_group_vec, SimilarityMode::Title).unwrap(), "\"some title\"");
+        assert_eq!(query_of(&parsed_title, &tag_group_vec, SimilarityMode::Artist).unwrap(), "~artist:\"artist a$\"");
+        assert_eq!(
+            query_of(&parsed_title, &tag_group_vec, SimilarityMode::Parody).unwrap(),
+            "~parody:\"touhou project$\" ~character:\"reimu hakurei$\"",
+        );
+
+        let tag_group_vec = [tag_group("parody", &["original"])];
+        assert_eq!(query_of(&parsed_title, &tag_group_vec, SimilarityMode::Artist), None);
+        assert_eq!(query_of(&parsed_title, &tag_group_vec, SimilarityMode::Parody), None);
+    }
+
+    #[test]
+    fn rank_test() {
+        let source_title = "[Artist] Some Long Title [English]".parse::<ParsedTitle>().unwrap();
+        let source_tag_vec = [String::from("artist:artist a"), String::from("female:glasses")];
+        let gallery_info_vec = vec![
+            info(1, "[Artist] Some Long Title [English]", None),
+            info(4, "[Other] Unrelated Book", Some(&["artist:other"])),
+            info(3, "[Artist] Some Long Title [Chinese]", Some(&["artist:artist a", "female:glasses"])),
+            info(2, "[Artist] Some Long Title 2", Some(&["artist:artist a"])),
+            info(5, "[Artist] Some Long Title [Korean]", None),
+        ];
+
+        let similar_vec = rank(gallery_info_vec, 1, &source_title, &source_tag_vec);
+        let gid_vec = similar_vec.iter().map(|similar| similar.gallery_info.identity.gid).collect::<Vec<u64>>();
+        assert_eq!(gid_vec, vec![3, 5, 2, 4]);
+        assert_eq!(similar_vec[0].score, 1.0);
+        assert!(similar_vec[3].score < 0.2);
+    }
+
+    #[test]
+    fn dice_coefficient_test() {
+        assert_eq!(dice_coefficient("some title", "some title"), 1.0);
+        assert_eq!(dice_coefficient("abc", "xyz"), 0.0);
+        assert!(dice_coefficient("some title", "some titles") > 0.9);
+        assert!(dice_coefficient("タイトル", "タイトル2") > 0.8);
+    }
+
+    #[test]
+    fn jaccard_index_test() {
+        let a = HashSet::from([String::from("artist:a"), String::from("parody:b")]);
+        let b = HashSet::from([String::from("artist:a"), String::from("female:c")]);
+        assert_eq!(jaccard_index(&a, &b), 1.0 / 3.0);
+        assert_eq!(jaccard_index(&HashSet::new(), &HashSet::new()), 0.0);
+    }
+}
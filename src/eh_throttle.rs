@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use reqwest::Url;
+use tokio::time::{self, Instant};
+use crate::{EhError, EhResult};
+
+/// A token bucket per host, so that a burst of calls doesn't get the IP banned.
+///
+/// Page loads and `api.php` calls are counted separately, each with its own rate.
+/// While a ban is known every request is refused up front, waiting it out
+/// instead of extending it.
+///
+/// One throttle can be shared by several `EhClient`s through an `Arc`,
+/// as they all talk to the server from the same address.
+#[derive(Debug)]
+pub struct EhThrottle {
+    page_requests_per_second: f64,
+    api_requests_per_second: f64,
+    burst: f64,
+    bucket_map: Mutex<HashMap<String, Bucket>>,
+    banned_until_opt: Mutex<Option<Instant>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Default for EhThrottle {
+    fn default() -> Self {
+        EhThrottle::new(DEFAULT_PAGE_REQUESTS_PER_SECOND, DEFAULT_API_REQUESTS_PER_SECOND)
+    }
+}
+
+impl EhThrottle {
+    pub fn new(page_requests_per_second: f64, api_requests_per_second: f64) -> EhThrottle {
+        EhThrottle {
+            page_requests_per_second,
+            api_requests_per_second,
+            burst: DEFAULT_BURST,
+            bucket_map: Mutex::new(HashMap::new()),
+            banned_until_opt: Mutex::new(None),
+        }
+    }
+
+    /// How many requests per host may be sent back to back, at least 1.
+    pub fn with_burst(mut self, burst: u32) -> EhThrottle {
+        self.burst = burst.max(1) as f64;
+        self
+    }
+
+    /// Wait until a request to `url` may be sent.
+    ///
+    /// Fails with `EhError::IpBanned` while a ban is known.
+    pub async fn acquire(&self, url: &str) -> EhResult<()> {
+        self.check_ban()?;
+
+        let (key, rate) = match Url::parse(url) {
+            Ok(url) if url.path().ends_with(API_PATH) => {
+                (format!("{}{}", url.host_str().unwrap_or_default(), API_PATH), self.api_requests_per_second)
+            }
+            Ok(url) => (String::from(url.host_str().unwrap_or_default()), self.page_requests_per_second),
+            Err(_) => (String::new(), self.page_requests_per_second),
+        };
+
+        loop {
+            let wait = {
+                let mut bucket_map = self.bucket_map.lock().unwrap();
+                let now = Instant::now();
+                let bucket = bucket_map.entry(key.clone()).or_insert(Bucket {
+                    tokens: self.burst,
+                    last_refill: now,
+                });
+
+                let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+                bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    return Ok(());
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / rate)
+            };
+
+            time::sleep(wait).await;
+        }
+    }
+
+    /// Remember a ban reported by the server, see `EhError::IpBanned`.
+    pub fn set_banned(&self, expires_in: Duration) {
+        *self.banned_until_opt.lock().unwrap() = Some(Instant::now() + expires_in);
+    }
+
+    /// Fails with the remaining time while a ban is known.
+    pub fn check_ban(&self) -> EhResult<()> {
+        let mut banned_until_opt = self.banned_until_opt.lock().unwrap();
+        if let Some(banned_until) = *banned_until_opt {
+            let now = Instant::now();
+            if now < banned_until {
+                return Err(EhError::IpBanned { expires_in: banned_until - now });
+            }
+
+            *banned_until_opt = None;
+        }
+
+        Ok(())
+    }
+}
+
+const API_PATH: &str = "/api.php";
+
+const DEFAULT_PAGE_REQUESTS_PER_SECOND: f64 = 3.0;
+const DEFAULT_API_REQUESTS_PER_SECOND: f64 = 5.0;
+const DEFAULT_BURST: f64 = 3.0;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn acquire_test() {
+        let throttle = EhThrottle::new(20.0, 1000.0).with_burst(1);
+        let start = Instant::now();
+        for _ in 0..5 {
+            throttle.acquire("https://e-hentai.org/").await.unwrap();
+        }
+
+        // the first one passes immediately, the other four wait 50ms each.
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        // api.php has a bucket of its own.
+        let start = Instant::now();
+        throttle.acquire("https://api.e-hentai.org/api.php").await.unwrap();
+        throttle.acquire("https://e-hentai.org/api.php").await.unwrap();
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn ban_test() {
+        let throttle = EhThrottle::default();
+        throttle.set_banned(Duration::from_secs(60));
+
+        match throttle.acquire("https://e-hentai.org/").await {
+            Err(EhError::IpBanned { expires_in }) => assert!(expires_in <= Duration::from_secs(60)),
+            _ => unreachable!(),
+        }
+
+        throttle.set_banned(Duration::ZERO);
+        assert!(throttle.check_ban().is_ok());
+    }
+}
@@ -6,67 +6,68 @@ use crate::settings::{GallerySites, Settings};
 #[derive(Debug, PartialEq, Clone)]
 pub struct EhUrl {
     settings: Settings,
+    /// Replaces the host of the site, to run against a local server in tests.
+    host_opt: Option<String>,
 }
 
 impl EhUrl {
     pub fn new(settings: Settings) -> EhUrl {
-        EhUrl { settings }
+        EhUrl { settings, host_opt: None }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn with_host(settings: Settings, host: &str) -> EhUrl {
+        EhUrl { settings, host_opt: Some(String::from(host)) }
+    }
+
+    fn host(&self) -> &str {
+        match (&self.host_opt, self.settings.site()) {
+            (Some(host), _) => host,
+            (None, GallerySites::E) => HOST_E,
+            (None, _) => HOST_EX,
+        }
     }
 
     pub fn gallery_detail(&self, gid: u64, token: &str, all_comment: bool, index_opt: Option<u32>) -> String {
-        let prefix = format!("/g/{}/{}", gid, token);
-        let mut prefix = match self.settings.site() {
-            GallerySites::E => format!("{}{}", HOST_E, prefix),
-            _ => format!("{}{}", HOST_EX, prefix),
-        };
+        let mut url = reqwest::Url::parse(self.host()).unwrap()
+            .join(&format!("g/{}/{}/", gid, token))
+            .unwrap();
 
         if all_comment {
-            prefix = prefix + "?hc=1";
+            url.query_pairs_mut().append_pair("hc", "1");
         }
 
         if let Some(index) = index_opt {
-            let suffix = if all_comment {
-                format!("&p={}", index)
-            } else {
-                format!("?p={}", index)
-            };
-
-            prefix = prefix + &suffix;
+            url.query_pairs_mut().append_pair("p", &index.to_string());
         }
 
-        prefix + "/"
+        String::from(url)
     }
 
     pub fn gallery_multi_page_viewer(&self, gid: u64, token: &str) -> String {
         let suffix = format!("?mpv/{}/{}/", gid, token);
-        match self.settings.site() {
-            GallerySites::E => format!("{}{}", HOST_E, suffix),
-            _ => format!("{}{}", HOST_EX, suffix),
-        }
+        format!("{}{}", self.host(), suffix)
     }
 
     pub fn page(&self, gid: u64, index: u32, p_token: &str) -> String {
         let suffix = format!("s/{}/{}-{}", p_token, gid, (index + 1));
-        match self.settings.site() {
-            GallerySites::E => format!("{}{}", HOST_E, suffix),
-            _ => format!("{}{}", HOST_EX, suffix),
-        }
+        format!("{}{}", self.host(), suffix)
     }
 
     pub fn add_favorites(&self, gid: u64, token: &str) -> String {
         let suffix = format!(r#"gallerypopups.php?gid={}&t={}&act=addfav"#, gid, token);
-        match self.settings.site() {
-            GallerySites::E => format!("{}{}", HOST_E, suffix),
-            _ => format!("{}{}", HOST_EX, suffix),
-        }
+        format!("{}{}", self.host(), suffix)
     }
 
     pub fn download_archive(&self, gid: u64, token: &str, or: &str) -> String {
         let suffix = format!(r#"archiver.php?gid={}&token={}&or={}"#, gid, token, or);
-        match self.settings.site() {
-            GallerySites::E => format!("{}{}", HOST_E, suffix),
-            _ => format!("{}{}", HOST_EX, suffix),
-        }
+        format!("{}{}", self.host(), suffix)
+    }
+
+    pub fn search(&self, query: &str) -> String {
+        let mut url = reqwest::Url::parse(self.host()).unwrap();
+        url.query_pairs_mut().append_pair("f_search", query);
+        String::from(url)
     }
 
     pub fn favorites(&self) -> String {
@@ -77,10 +78,7 @@ impl EhUrl {
     }
 
     pub fn api(&self) -> String {
-        match self.settings.site() {
-            GallerySites::E => String::from(API_E),
-            _ => String::from(API_EX),
-        }
+        format!("{}api.php", self.host())
     }
 
     pub fn referer(&self) -> String {
@@ -147,12 +145,11 @@ pub const DOMAIN_E: &str = "e-hentai.org";
 pub const DOMAIN_LOFI: &str = "lofi.e-hentai.org";
 pub const API_SIGN_IN: &str = "https://forums.e-hentai.org/index.php?act=Login&CODE=01";
 pub const URL_FORUMS: &str = "https://forums.e-hentai.org/";
+/// Only served by e-hentai.org, whatever the site.
+pub const URL_HOME: &str = "https://e-hentai.org/home.php";
 
-const HOST_EX: &str = CONCAT!("https://", DOMAIN_EX, "/");
-const HOST_E: &str = CONCAT!("https://", DOMAIN_E, "/");
-
-const API_E: &str = CONCAT!(HOST_E, "api.php");
-const API_EX: &str = CONCAT!(HOST_EX, "api.php");
+pub const HOST_EX: &str = CONCAT!("https://", DOMAIN_EX, "/");
+pub const HOST_E: &str = CONCAT!("https://", DOMAIN_E, "/");
 
 const URL_POPULAR_E: &str = "https://e-hentai.org/popular";
 const URL_POPULAR_EX: &str = "https://exhentai.org/popular";
@@ -182,3 +179,21 @@ const URL_WATCHED_EX: &str = CONCAT!(HOST_EX, "watched");
 
 const URL_PREFIX_THUMB_E: &str = "https://ehgt.org/";
 // const URL_PREFIX_THUMB_EX: &str = "https://exhentai.org/t/";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gallery_detail_test() {
+        let eh_url = EhUrl::new(Settings::default());
+        assert_eq!(eh_url.gallery_detail(2062067, "588c82702b", false, None), "https://e-hentai.org/g/2062067/588c82702b/");
+        assert_eq!(eh_url.gallery_detail(2062067, "588c82702b", false, Some(1)), "https://e-hentai.org/g/2062067/588c82702b/?p=1");
+        assert_eq!(eh_url.gallery_detail(2062067, "588c82702b", true, Some(2)), "https://e-hentai.org/g/2062067/588c82702b/?hc=1&p=2");
+
+        let mut settings = Settings::default();
+        settings.set_site(GallerySites::EX);
+        let eh_url = EhUrl::new(settings);
+        assert_eq!(eh_url.gallery_detail(2062067, "588c82702b", true, None), "https://exhentai.org/g/2062067/588c82702b/?hc=1");
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use crate::{
+    EhResult,
+    eh_downloader,
+    parser::ParseError,
+    structures::GalleryPageUrl,
+};
+
+/// Written by the `Downloader` next to the images, it keeps the page tokens
+/// the files are checked against.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct DownloadManifest {
+    pub gid: u64,
+    pub pages: u32,
+    pub page_url_vec: Vec<GalleryPageUrl>,
+    /// Pages saved from the resampled image, which cannot be checked: the
+    /// page token is the hash of the original.
+    pub resampled_page_set: BTreeSet<u32>,
+}
+
+/// The result of `verify_gallery`, pages are 0-based.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct GalleryVerification {
+    pub verified_page_vec: Vec<u32>,
+    /// Files whose hash is not the one of their page token.
+    pub corrupt_vec: Vec<(u32, PathBuf)>,
+    pub missing_page_vec: Vec<u32>,
+    pub resampled_page_vec: Vec<u32>,
+}
+
+impl DownloadManifest {
+    /// `None` if `dir` has no manifest.
+    pub fn load<P: AsRef<Path>>(dir: P) -> EhResult<Option<DownloadManifest>> {
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let s = fs::read_to_string(path)?;
+        let manifest = serde_json::from_str::<DownloadManifest>(&s).map_err(ParseError::from)?;
+        Ok(Some(manifest))
+    }
+
+    /// Written aside then renamed, a crash never leaves half a manifest.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> EhResult<()> {
+        let s = serde_json::to_string_pretty(self).map_err(ParseError::from)?;
+        let path = dir.as_ref().join(MANIFEST_FILE);
+        let partial_path = path.with_extension(PARTIAL_EXTENSION);
+        fs::write(&partial_path, s)?;
+        fs::rename(&partial_path, &path)?;
+
+        Ok(())
+    }
+
+    /// Record the urls of a download of a gallery of `pages` pages, keeping
+    /// the pages recorded before.
+    pub fn add_page_urls(&mut self, page_url_vec: &[GalleryPageUrl], pages: u32) {
+        if let Some(page_url) = page_url_vec.first() {
+            self.gid = page_url.gid;
+        }
+
+        self.pages = pages;
+        self.page_url_vec.retain(|page_url| page_url_vec.iter().all(|new| new.page != page_url.page));
+        self.page_url_vec.extend(page_url_vec.iter().cloned());
+        self.page_url_vec.sort_by_key(|page_url| page_url.page);
+    }
+
+    pub fn set_resampled(&mut self, page: u32, resampled: bool) {
+        if resampled {
+            self.resampled_page_set.insert(page);
+        } else {
+            self.resampled_page_set.remove(&page);
+        }
+    }
+}
+
+impl GalleryVerification {
+    pub fn is_complete(&self) -> bool {
+        self.corrupt_vec.is_empty() && self.missing_page_vec.is_empty()
+    }
+
+    /// Delete the corrupt files, the next download of the folder fetches them again.
+    pub fn remove_corrupt_files(&self) -> EhResult<()> {
+        for (_, path) in &self.corrupt_vec {
+            fs::remove_file(path)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The first 10 hex characters of the SHA-1 of `bytes`, the same as the
+/// page token of the original image.
+pub fn sha1_prefix(bytes: &[u8]) -> String {
+    let digest = Sha1::digest(bytes);
+    digest.iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>()[..P_TOKEN_LEN]
+        .to_string()
+}
+
+pub fn matches_p_token(bytes: &[u8], p_token: &str) -> bool {
+    sha1_prefix(bytes) == p_token
+}
+
+/// Check every file of a folder written by the `Downloader` against the page
+/// tokens recorded in its manifest.
+pub fn verify_gallery<P: AsRef<Path>>(dir: P) -> EhResult<GalleryVerification> {
+    let dir = dir.as_ref();
+    let manifest = DownloadManifest::load(dir)?
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no download manifest"))?;
+    let file_map = eh_downloader::existing_file_map(dir)?;
+
+    let mut verification = GalleryVerification::default();
+    for page_url in &manifest.page_url_vec {
+        let page = page_url.page;
+        let Some(path) = file_map.get(&eh_downloader::page_file_stem(page, manifest.pages)) else {
+            verification.missing_page_vec.push(page);
+            continue;
+        };
+
+        if manifest.resampled_page_set.contains(&page) {
+            verification.resampled_page_vec.push(page);
+        } else if matches_p_token(&fs::read(path)?, &page_url.p_token) {
+            verification.verified_page_vec.push(page);
+        } else {
+            verification.corrupt_vec.push((page, path.clone()));
+        }
+    }
+
+    Ok(verification)
+}
+
+pub const MANIFEST_FILE: &str = "manifest.json";
+const P_TOKEN_LEN: usize = 10;
+const PARTIAL_EXTENSION: &str = "part";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_prefix_test() {
+        assert_eq!(sha1_prefix(b""), "da39a3ee5e");
+        assert!(matches_p_token(b"abc", "a9993e3647"));
+        assert!(!matches_p_token(b"abd", "a9993e3647"));
+    }
+
+    #[test]
+    fn verify_gallery_test() {
+        let dir = std::env::temp_dir().join(format!("eh_verify_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let page_url = |page: u32, p_token: &str| GalleryPageUrl { gid: 1, p_token: String::from(p_token), page };
+        let manifest = DownloadManifest {
+            gid: 1,
+            pages: 4,
+            page_url_vec: vec![page_url(0, "a9993e3647"), page_url(1, "a9993e3647"), page_url(2, "da39a3ee5e"), page_url(3, "0000000000")],
+            resampled_page_set: BTreeSet::from([3]),
+        };
+        manifest.save(&dir).unwrap();
+        assert!(!dir.join(MANIFEST_FILE).with_extension(PARTIAL_EXTENSION).exists());
+        assert_eq!(DownloadManifest::load(&dir).unwrap(), Some(manifest.clone()));
+
+        fs::write(dir.join("001.jpg"), b"abc").unwrap();
+        fs::write(dir.join("002.png"), b"abd").unwrap();
+        fs::write(dir.join("004.jpg"), b"resampled").unwrap();
+
+        let verification = verify_gallery(&dir).unwrap();
+        assert_eq!(verification.verified_page_vec, vec![0]);
+        assert_eq!(verification.corrupt_vec, vec![(1, dir.join("002.png"))]);
+        assert_eq!(verification.missing_page_vec, vec![2]);
+        assert_eq!(verification.resampled_page_vec, vec![3]);
+        assert!(!verification.is_complete());
+
+        verification.remove_corrupt_files().unwrap();
+        assert!(!dir.join("002.png").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
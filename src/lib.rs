@@ -1,6 +1,18 @@
 mod eh_url;
-mod settings;
-mod eh_client;
+pub mod settings;
+pub mod eh_client;
+pub mod eh_client_builder;
+pub mod eh_session;
+pub mod eh_throttle;
+pub mod eh_retry;
+pub mod eh_account_pool;
+pub mod eh_similar;
+pub mod eh_downloader;
+pub mod eh_download_queue;
+pub mod eh_verify;
+pub mod eh_cbz;
+pub mod eh_epub;
+pub mod eh_pdf;
 pub mod parser;
 mod test_helper;
 mod eh_config;
@@ -11,6 +23,22 @@ pub enum EhError {
     ParseError(parser::ParseError),
     EngineError(reqwest::Error),
     FromServerError(parser::ParseError),
+    IoError(std::io::Error),
+    /// The account cannot browse exhentai.org, the server answered with
+    /// an empty page or the sad panda.
+    ExhentaiAccessDenied,
+    SignInFailed(structures::SignInError),
+    /// The server banned this IP for excessive page loads.
+    IpBanned { expires_in: std::time::Duration },
+    /// The gallery is behind the offensive content warning,
+    /// see `settings::ContentWarning` to skip it.
+    ContentWarning,
+    /// The gallery cannot be viewed, on this site at least.
+    GalleryNotAvailable(structures::GalleryUnavailable),
+    /// The image quota of the account is used up, see `EhClient::get_image_limits`.
+    ImageQuotaExceeded,
+    /// The SHA-1 of a downloaded image does not start with its page token.
+    HashMismatch { expected: String, actual: String },
 }
 
 impl std::fmt::Display for EhError {
@@ -19,6 +47,14 @@ impl std::fmt::Display for EhError {
             EhError::ParseError(e) => e.fmt(f),
             EhError::EngineError(e) => e.fmt(f),
             EhError::FromServerError(e) => e.fmt(f),
+            EhError::IoError(e) => e.fmt(f),
+            EhError::ExhentaiAccessDenied => write!(f, "this account has no access to exhentai"),
+            EhError::SignInFailed(e) => e.fmt(f),
+            EhError::IpBanned { expires_in } => write!(f, "ip address is banned, expires in {} seconds", expires_in.as_secs()),
+            EhError::ContentWarning => write!(f, "this gallery is behind a content warning"),
+            EhError::GalleryNotAvailable(kind) => kind.fmt(f),
+            EhError::ImageQuotaExceeded => write!(f, "the image quota is exceeded"),
+            EhError::HashMismatch { expected, actual } => write!(f, "image hash {} does not match the page token {}", actual, expected),
         }
     }
 }
@@ -31,10 +67,20 @@ impl From<reqwest::Error> for EhError {
     }
 }
 
+impl From<std::io::Error> for EhError {
+    fn from(value: std::io::Error) -> Self {
+        EhError::IoError(value)
+    }
+}
+
 impl From<parser::ParseError> for EhError {
     fn from(value: parser::ParseError) -> Self {
         match value {
             parser::ParseError::FromServer(_) => EhError::FromServerError(value),
+            parser::ParseError::ContentWarning => EhError::ContentWarning,
+            parser::ParseError::GalleryPining => EhError::GalleryNotAvailable(structures::GalleryUnavailable::Expunged { reason_opt: None }),
+            parser::ParseError::GalleryNotAvailable(kind) => EhError::GalleryNotAvailable(kind),
+            parser::ParseError::ImageQuotaExceeded => EhError::ImageQuotaExceeded,
             _ => EhError::ParseError(value),
         }
     }
@@ -36,6 +36,14 @@ impl FromStr for Category {
     }
 }
 
+impl Category {
+    /// The category of a `GalleryInfo::category` or `GalleryDetail::category` value.
+    pub fn from_value(value: u32) -> Category {
+        // never fails, unknown values are the unknown category.
+        Category::from_u32(value).unwrap()
+    }
+}
+
 pub trait FromU32: Sized {
     type Err;
 
@@ -80,12 +88,6 @@ impl U32ParseEx for u32 {
 // const ALL_CATEGORY: u32 = VALUE_UNKNOWN - 1;
 
 
-// Remove [XXX], (XXX), {XXX}, ~XXX~ stuff
-// const PATTERN_TITLE_PREFIX: &str = r#"^(?:(?:\([^\)]*\))|(?:\[[^\]]*\])|(?:\{[^\}]*\})|(?:~[^~]*~)|\s+)*"#;
-
-// Remove [XXX], (XXX), {XXX}, ~XXX~ stuff and something like ch. 1-23
-// const PATTERN_TITLE_SUFFIX: &str = r#"(?:\s+ch.[\s\d-]+)?(?:(?:\([^\)]*\))|(?:\[[^\]]*\])|(?:\{[^\}]*\})|(?:~[^~]*~)|\s+)*$"#;
-
 // DOUJINSHI|MANGA|ARTIST_CG|GAME_CG|WESTERN|NON_H|IMAGE_SET|COSPLAY|ASIAN_PORN|MISC;
 
 const CATEGORY_COLORS: [u32; 11] = [
@@ -1,12 +1,7 @@
 use std::str::FromStr;
+use regex::Regex;
 use visdom::Vis;
-use crate::parser::{ATTRIBUTE_NOT_FOUND, ParseError};
-
-#[derive(Debug, PartialEq)]
-pub struct Forums {
-    /// Links to user profile page.
-    pub user_link: String,
-}
+use crate::{parser::{ATTRIBUTE_NOT_FOUND, ParseError, REGEX_MATCH_FAILED}, structures::Forums};
 
 impl FromStr for Forums {
     type Err = ParseError;
@@ -23,10 +18,16 @@ impl FromStr for Forums {
         let href = user_link.attr("href").ok_or(ATTRIBUTE_NOT_FOUND)?;
         let user_link = href.to_string();
 
-        Ok(Forums { user_link })
+        let regex = Regex::new(PATTERN_MEMBER_ID).unwrap();
+        let captures = regex.captures(&user_link).ok_or(REGEX_MATCH_FAILED)?;
+        let member_id = captures[1].parse::<u64>()?;
+
+        Ok(Forums { user_link, member_id })
     }
 }
 
+const PATTERN_MEMBER_ID: &str = r#"showuser=(\d+)"#;
+
 #[cfg(test)]
 mod tests {
     use crate::test_helper::read_test_file;
@@ -37,4 +38,15 @@ mod tests {
         let s = read_test_file("forums.html");
         assert_eq!(s.parse::<Forums>().is_ok(), true);
     }
+
+    #[test]
+    fn member_id_parse_test() {
+        let ele = r#"
+            <div id="userlinks"><p class="home"><b>Logged in as:  <a href="https://forums.e-hentai.org/index.php?showuser=1234567">xxxx</a></b></p></div>
+        "#;
+        assert_eq!(ele.parse::<Forums>().unwrap(), Forums {
+            user_link: String::from("https://forums.e-hentai.org/index.php?showuser=1234567"),
+            member_id: 1234567,
+        });
+    }
 }
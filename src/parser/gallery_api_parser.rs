@@ -1 +1,88 @@
- 
+use std::str::FromStr;
+use crate::{parser::ParseError, structures::{GalleryMetadata, GalleryMetadataList, GalleryUnavailable}};
+
+impl FromStr for GalleryMetadataList {
+    type Err = ParseError;
+
+    /// ```json
+    /// {
+    ///     "gmetadata": [
+    ///         {
+    ///             "gid": 2062067,
+    ///             "token": "588c82702b",
+    ///             "title": "...",
+    ///             "expunged": false,
+    ///             ...
+    ///         },
+    ///         {
+    ///             "gid": 2062068,
+    ///             "error": "Key missing, or incorrect key provided."
+    ///         }
+    ///     ]
+    /// }
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(serde_json::from_str(s)?)
+    }
+}
+
+impl GalleryMetadata {
+    /// Why the gallery cannot be viewed, `None` if it can.
+    pub fn unavailable_opt(&self) -> Option<GalleryUnavailable> {
+        if let Some(error) = &self.error_opt {
+            return error.parse::<GalleryUnavailable>().ok();
+        }
+
+        if self.expunged {
+            return Some(GalleryUnavailable::Expunged { reason_opt: None });
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let json = r#"
+            {
+                "gmetadata": [
+                    {
+                        "gid": 2062067,
+                        "token": "588c82702b",
+                        "archiver_key": "xxxx",
+                        "title": "[Artist] Title",
+                        "title_jpn": "",
+                        "category": "Doujinshi",
+                        "thumb": "https://ehgt.org/xx/xx/xxxx-xxxx.jpg",
+                        "uploader": "uploader",
+                        "posted": "1638000000",
+                        "filecount": "24",
+                        "filesize": 12345678,
+                        "expunged": true,
+                        "rating": "4.52",
+                        "torrentcount": "0",
+                        "tags": ["language:english"]
+                    },
+                    {
+                        "gid": 2062068,
+                        "error": "Key missing, or incorrect key provided."
+                    }
+                ]
+            }
+        "#;
+
+        let list = json.parse::<GalleryMetadataList>().unwrap();
+        assert_eq!(list.metadata_vec.len(), 2);
+
+        let metadata = &list.metadata_vec[0];
+        assert_eq!(metadata.file_count, "24");
+        assert_eq!(metadata.tag_vec, vec![String::from("language:english")]);
+        assert_eq!(metadata.unavailable_opt(), Some(GalleryUnavailable::Expunged { reason_opt: None }));
+
+        assert_eq!(list.metadata_vec[1].unavailable_opt(), Some(GalleryUnavailable::InvalidKey));
+    }
+}
@@ -1,14 +1,13 @@
-use std::collections::HashMap;
 use std::iter::zip;
 use std::str::FromStr;
-use chrono::DateTime;
+use chrono::{DateTime, Utc};
 use regex::Regex;
-use visdom::Vis;
+use visdom::{Vis, types::Elements};
 use crate::{
-    parser::{ATTRIBUTE_NOT_FOUND, DOM_NOT_FOUND, ParseError, REGEX_MATCH_FAILED, unescape::unescape},
+    parser::{ATTRIBUTE_NOT_FOUND, CONTENT_WARNING, DOM_NOT_FOUND, GALLERY_PINING, ParseError, REGEX_MATCH_FAILED, parse_file_size, parse_posted, unescape::unescape},
     structures::{Category, FavoriteSlot, GalleryComment, GalleryCommentList, GalleryDetail,
-                 GalleryDetailDetail, GalleryIdentity, GalleryPreviewLarge, GalleryPreviewMedium,
-                 GalleryPreviewSet, GalleryTagGroupList},
+                 GalleryDetailDetail, GalleryIdentity, GalleryNotAvailable, GalleryPageUrl, GalleryPreviewLarge, GalleryPreviewMedium,
+                 GalleryPreviewSet, GalleryTagGroupList, GalleryVersion, HiddenReason, Language, LanguageDetection, LanguageVariant, ParsedTitle, Visible},
 };
 
 impl FromStr for GalleryDetail {
@@ -16,16 +15,17 @@ impl FromStr for GalleryDetail {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         if s.contains(OFFENSIVE_STRING) {
-            return Err(ParseError::FromServer(String::from("if you choose to ignore this warning, you lose all rights to complain about it in the future.")));
+            return Err(CONTENT_WARNING);
         }
 
         if s.contains(PINING_STRING) {
-            return Err(ParseError::FromServer(String::from("this gallery_list is pining for the fjords.")));
+            return Err(GALLERY_PINING);
         }
 
         let regex = Regex::new(PATTERN_ERROR).unwrap();
-        if let Some(cap) = regex.captures(s) {
-            return Err(ParseError::FromServer(String::from(&cap[1])));
+        if regex.is_match(s) {
+            let not_available = s.parse::<GalleryNotAvailable>()?;
+            return Err(ParseError::GalleryNotAvailable(not_available.kind));
         }
 
         // 1. identity
@@ -117,24 +117,12 @@ impl FromStr for GalleryDetail {
             (None, None)
         };
 
-        // 18. newer_version_map_opt
+        // 18. newer_version_vec
         let gnd = root.find("#gnd");
-        let newer_version_map_opt = if !gnd.is_empty() {
-            let regex = Regex::new(PATTERN_NEWER_DATE).unwrap();
-            let date_vec = regex.captures_iter(s)
-                .map(|cap| String::from(&cap[1]))
-                .collect::<Vec<String>>();
-
-            let mut newer_version_map = HashMap::new();
-            let hrefs = gnd.find("a");
-            for (idx, href) in hrefs.into_iter().enumerate() {
-                let href = href.get_attribute("href").ok_or(ATTRIBUTE_NOT_FOUND)?;
-                let identity = href.to_string().parse::<GalleryIdentity>()?;
-                newer_version_map.insert(date_vec[idx].clone(), identity);
-            }
-            Some(newer_version_map)
+        let newer_version_vec = if !gnd.is_empty() {
+            parse_newer_version(&gnd.html())?
         } else {
-            None
+            vec![]
         };
 
         // 19. comment_list
@@ -151,15 +139,7 @@ impl FromStr for GalleryDetail {
         let url = href.to_string();
 
         // 22. preview_set
-        let gdo4 = root.find("#gdo4");
-        let selected = gdo4.children(".ths");
-
-        let gdt = root.find("#gdt");
-        let preview_set = match selected.text().as_str() {
-            "Large" => GalleryPreviewSet::Large(parse_large(&gdt.outer_html())?),
-            "Normal" => GalleryPreviewSet::Medium(parse_medium(&gdt.outer_html())?),
-            _ => unreachable!(),
-        };
+        let preview_set = parse_preview_set(&root)?;
 
         // 23. tag_group_vec
         let tag_list = root.find("#taglist");
@@ -174,7 +154,7 @@ impl FromStr for GalleryDetail {
             torrent_url,
             archive_url,
             thumb,
-            newer_version_map_opt,
+            newer_version_vec,
             is_favorited,
             favorite_slot_opt,
             favorite_name_opt,
@@ -424,31 +404,43 @@ impl FromStr for GalleryDetailDetail {
             mut pages,
             mut favorite_count
         ) = (None, None, None, None, None, None, None);
+        let (mut is_translated, mut is_rewrite) = (false, false);
 
         for gdt1 in gdt1s {
             match gdt1.text().as_str() {
                 "Posted:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
-                    posted = Some(gdt2.text());
+                    posted = Some(parse_posted(&gdt2.text())?);
                 }
                 "Parent:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
 
-                    if let Some(href) = gdt2.get_attribute("href") {
-                        parent_opt = Some(href.to_string());
+                    let a = gdt2.children().filter("a");
+                    if let Some(href) = a.attr("href") {
+                        parent_opt = Some(href.to_string().parse::<GalleryIdentity>()?);
                     }
                 }
                 "Visible:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
-                    visible = Some(gdt2.text());
+                    visible = Some(gdt2.text().parse::<Visible>()?);
                 }
                 "Language:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
-                    language = Some(gdt2.text());
+                    // `English &nbsp;TR`, a translation.
+                    let text = gdt2.text();
+                    let mut word_iter = text.split_whitespace();
+                    language = Some(word_iter.next().unwrap_or_default().parse::<Language>()?);
+                    for word in word_iter {
+                        match word {
+                            TRANSLATED_STRING => is_translated = true,
+                            REWRITE_STRING => is_rewrite = true,
+                            _ => {}
+                        }
+                    }
                 }
                 "File Size:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
-                    file_size = Some(gdt2.text());
+                    file_size = Some(parse_file_size(&gdt2.text())?);
                 }
                 "Length:" => {
                     let gdt2 = gdt1.next_element_sibling().unwrap();
@@ -483,6 +475,8 @@ impl FromStr for GalleryDetailDetail {
                 parent_opt,
                 visible,
                 language,
+                is_translated,
+                is_rewrite,
                 file_size,
                 pages,
                 favorite_count,
@@ -493,6 +487,146 @@ impl FromStr for GalleryDetailDetail {
     }
 }
 
+impl FromStr for GalleryPreviewSet {
+    type Err = ParseError;
+
+    /// Any page of a gallery, `?p=1` and the following ones included.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(OFFENSIVE_STRING) {
+            return Err(CONTENT_WARNING);
+        }
+
+        let root = Vis::load(s)?;
+        parse_preview_set(&root)
+    }
+}
+
+impl GalleryPreviewSet {
+    /// The page urls of the previews, in order.
+    pub fn page_url_vec(&self) -> Result<Vec<GalleryPageUrl>, ParseError> {
+        let page_url_iter: Box<dyn Iterator<Item=&String>> = match self {
+            GalleryPreviewSet::Large(preview_vec) => Box::new(preview_vec.iter().map(|p| &p.page_url)),
+            GalleryPreviewSet::Medium(preview_vec) => Box::new(preview_vec.iter().map(|p| &p.page_url)),
+        };
+
+        page_url_iter.map(|page_url| page_url.parse::<GalleryPageUrl>()).collect()
+    }
+}
+
+impl GalleryDetail {
+    /// This gallery as an entry of its version chain.
+    pub fn version(&self) -> Result<GalleryVersion, ParseError> {
+        Ok(GalleryVersion {
+            identity: self.identity.clone(),
+            title: self.title.clone(),
+            posted: self.detail.posted,
+        })
+    }
+
+    pub fn parsed_title(&self) -> ParsedTitle {
+        self.title.parse::<ParsedTitle>().unwrap_or_default()
+    }
+
+    /// `None` if the gallery has no japanese title.
+    pub fn parsed_title_jpn_opt(&self) -> Option<ParsedTitle> {
+        if self.title_jpn.trim().is_empty() {
+            return None;
+        }
+
+        self.title_jpn.parse::<ParsedTitle>().ok()
+    }
+
+    /// Every tag as `namespace:tag`.
+    pub fn tag_vec(&self) -> Vec<String> {
+        self.tag_group_vec.iter()
+            .flat_map(|group| group.tag_vec.iter().map(move |tag| format!("{}:{}", group.tag_group_name, tag)))
+            .collect()
+    }
+
+    /// The tags of `namespace`, without it.
+    pub fn tags_of(&self, namespace: &str) -> Vec<String> {
+        self.tag_group_vec.iter()
+            .filter(|group| group.tag_group_name == namespace)
+            .flat_map(|group| group.tag_vec.iter().cloned())
+            .collect()
+    }
+
+    /// Detect the language from the language tags, the title, then the Language field.
+    pub fn language_detection(&self) -> Option<LanguageDetection> {
+        let tag_vec = self.tag_group_vec.iter()
+            .filter(|group| group.tag_group_name == "language")
+            .flat_map(|group| group.tag_vec.iter().map(|tag| format!("language:{}", tag)))
+            .collect::<Vec<String>>();
+
+        let variant = if self.detail.is_rewrite {
+            LanguageVariant::Rewrite
+        } else if self.detail.is_translated {
+            LanguageVariant::Translated
+        } else {
+            LanguageVariant::Original
+        };
+
+        LanguageDetection::detect(&tag_vec, &self.title, Some((self.detail.language, variant)))
+    }
+}
+
+/// ```html
+/// There are newer versions of this gallery available:<br><br>
+/// <a href="https://e-hentai.org/g/2062068/4f9a3c81d2/">Title</a>, added 2021-11-28 10:12<br>
+/// ```
+fn parse_newer_version(gnd: &str) -> Result<Vec<GalleryVersion>, ParseError> {
+    let root = Vis::load(gnd)?;
+    let a_vec = root.find("a");
+
+    let regex = Regex::new(PATTERN_NEWER_DATE).unwrap();
+    let posted_vec = regex.captures_iter(gnd)
+        .map(|cap| parse_posted(&cap[1]))
+        .collect::<Result<Vec<DateTime<Utc>>, ParseError>>()?;
+
+    if a_vec.length() != posted_vec.len() {
+        return Err(REGEX_MATCH_FAILED);
+    }
+
+    let mut version_vec = vec![];
+    for (a, posted) in zip(a_vec, posted_vec) {
+        let href = a.get_attribute("href").ok_or(ATTRIBUTE_NOT_FOUND)?;
+        let identity = href.to_string().parse::<GalleryIdentity>()?;
+        let title = a.text();
+
+        version_vec.push(GalleryVersion { identity, title, posted });
+    }
+
+    Ok(version_vec)
+}
+
+impl FromStr for Visible {
+    type Err = ParseError;
+
+    /// ```text
+    /// Yes
+    /// No (Replaced)
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "Yes" {
+            return Ok(Visible::Yes);
+        }
+
+        let regex = Regex::new(PATTERN_VISIBLE_NO).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let reason = captures.get(1).map_or("", |m| m.as_str());
+
+        let reason = match reason {
+            "Replaced" => HiddenReason::Replaced,
+            "Expunged" => HiddenReason::Expunged,
+            "Private" => HiddenReason::Private,
+            _ => HiddenReason::Other(String::from(reason)),
+        };
+
+        Ok(Visible::No(reason))
+    }
+}
+
 impl FromStr for GalleryPreviewLarge {
     type Err = ParseError;
 
@@ -545,10 +679,24 @@ impl FromStr for GalleryPreviewLarge {
 ///     <div class="c"></div>
 /// </div>
 /// ```
+fn parse_preview_set(root: &Elements) -> Result<GalleryPreviewSet, ParseError> {
+    let gdo4 = root.find("#gdo4");
+    let selected = gdo4.children(".ths");
+
+    let gdt = root.find("#gdt");
+    let preview_set = match selected.text().as_str() {
+        "Large" => GalleryPreviewSet::Large(parse_large(&gdt.outer_html())?),
+        "Normal" => GalleryPreviewSet::Medium(parse_medium(&gdt.outer_html())?),
+        _ => return Err(DOM_NOT_FOUND),
+    };
+
+    Ok(preview_set)
+}
+
 fn parse_large(s: &str) -> Result<Vec<GalleryPreviewLarge>, ParseError> {
     let root = Vis::load(s)?;
     let mut preview_vec = Vec::new();
-    let gdt_larges = root.children(r#".gdtl"#);
+    let gdt_larges = root.find(r#".gdtl"#);
     for gdt_large in gdt_larges {
         preview_vec.push(gdt_large.outer_html().parse::<GalleryPreviewLarge>()?);
     }
@@ -611,13 +759,16 @@ fn parse_medium(s: &str) -> Result<Vec<GalleryPreviewMedium>, ParseError> {
 }
 
 const OFFENSIVE_STRING: &str = "<p>(And if you choose to ignore this warning, you lose all rights to complain about it in the future.)</p>";
-const PINING_STRING: &str = "<p>This gallery_list is pining for the fjords.</p>";
+const PINING_STRING: &str = "<p>This gallery is pining for the fjords.</p>";
 const PATTERN_ERROR: &str = "<div class=\"d\">\n<p>([^<]+)</p>";
 const PATTERN_DETAIL: &str = r#"var gid = (\d+);\s*?(\n|\r|\r\n)?\s*?var token = "([a-f0-9]+)";\s*?(\n|\r|\r\n)?\s*?var apiuid = ([\-\d]+);\s*?(\n|\r|\r\n)?\s*?var apikey = "([a-f0-9]+)";"#;
 const PATTERN_TORRENT: &str = r#"<a[^<>]*onclick="return popUp\('([^']+)'[^)]+\)">Torrent Download[^<]+(\d+)[^<]+</a"#;
 const PATTERN_ARCHIVE: &str = r#"<a[^<>]*onclick="return popUp\('([^']+)'[^)]+\)">Archive Download</a>"#;
 const PATTERN_RATING: &str = r#"[+-]?([0-9]*[.]?[0-9]+)"#;
-const PATTERN_NEWER_DATE: &str = ", added (.+?)<br />";
+const PATTERN_VISIBLE_NO: &str = r"^No(?: \((.+?)\))?$";
+const TRANSLATED_STRING: &str = "TR";
+const REWRITE_STRING: &str = "RW";
+const PATTERN_NEWER_DATE: &str = r", added (\d{4}-\d{2}-\d{2} \d{2}:\d{2})";
 const PATTERN_COVER: &str = r#"width:(\d+)px; height:(\d+)px.+?url\((.+?)\)"#;
 const PATTERN_FILENAME: &str = r#"Page \d+: ([\w\s]+.[\w]+)"#;
 // const PATTERN_PAGES: &str = r#"<tr><td[^<>]*>Length:</td><td[^<>]*>([\d,]+) pages</td></tr>"#;
@@ -625,7 +776,7 @@ const PATTERN_MEDIUM_PREVIEW: &str = r#"<div class="gdtm"[^<>]*><div[^<>]*width:
 
 #[cfg(test)]
 mod tests {
-    use crate::test_helper::read_test_file;
+    use crate::{structures::GalleryUnavailable, test_helper::read_test_file};
     use super::*;
 
     #[test]
@@ -634,6 +785,18 @@ mod tests {
         assert_eq!(s.parse::<GalleryDetail>().is_ok(), true);
     }
 
+    #[test]
+    fn parse_warning_test() {
+        let s = r#"<div class="d"><p>(And if you choose to ignore this warning, you lose all rights to complain about it in the future.)</p></div>"#;
+        assert!(matches!(s.parse::<GalleryDetail>(), Err(ParseError::ContentWarning)));
+
+        let s = r#"<div class="d"><p>This gallery is pining for the fjords.</p></div>"#;
+        assert!(matches!(s.parse::<GalleryDetail>(), Err(ParseError::GalleryPining)));
+
+        let s = "<div class=\"d\">\n<p>Key missing, or incorrect key provided.</p></div>";
+        assert!(matches!(s.parse::<GalleryDetail>(), Err(ParseError::GalleryNotAvailable(GalleryUnavailable::InvalidKey))));
+    }
+
     #[test]
     fn parse_detail_detail_test() {
         let table = r#"
@@ -670,6 +833,56 @@ mod tests {
         "#;
 
         assert_eq!(table.parse::<GalleryDetailDetail>().is_ok(), true);
+
+        let table = r#"
+        <table>
+            <tr><td class="gdt1">Posted:</td><td class="gdt2">2023-02-07 07:33</td></tr>
+            <tr><td class="gdt1">Parent:</td><td class="gdt2"><a href="https://e-hentai.org/g/2062067/588c82702b/">2062067</a></td></tr>
+            <tr><td class="gdt1">Visible:</td><td class="gdt2">No (Replaced)</td></tr>
+            <tr><td class="gdt1">Language:</td><td class="gdt2">English &nbsp;<span class="halp" title="This gallery has been translated from the original language text.">TR</span></td></tr>
+            <tr><td class="gdt1">File Size:</td><td class="gdt2">345.2 MiB</td></tr>
+            <tr><td class="gdt1">Length:</td><td class="gdt2">75 pages</td></tr>
+            <tr><td class="gdt1">Favorited:</td><td class="gdt2" id="favcount">23 times</td></tr>
+        </table>
+        "#;
+
+        let detail = table.parse::<GalleryDetailDetail>().unwrap();
+        assert_eq!(detail.parent_opt, Some(GalleryIdentity {
+            gid: 2062067,
+            token: String::from("588c82702b"),
+        }));
+        assert_eq!(detail.posted.timestamp(), 1675755180);
+        assert_eq!(detail.visible, Visible::No(HiddenReason::Replaced));
+        assert_eq!(detail.language, Language::English);
+        assert!(detail.is_translated);
+        assert_eq!(detail.file_size, 361968435);
+    }
+
+    #[test]
+    fn parse_file_size_test() {
+        assert_eq!(parse_file_size("225.5 MB").unwrap(), 236453888);
+        assert_eq!(parse_file_size("812 KiB").unwrap(), 831488);
+        assert_eq!(parse_file_size("1.02 GiB").unwrap(), 1095216660);
+        assert!(parse_file_size("unknown").is_err());
+    }
+
+    #[test]
+    fn parse_visible_test() {
+        assert_eq!("Yes".parse::<Visible>().unwrap(), Visible::Yes);
+        assert_eq!("No (Expunged)".parse::<Visible>().unwrap(), Visible::No(HiddenReason::Expunged));
+        assert_eq!("No (Private)".parse::<Visible>().unwrap(), Visible::No(HiddenReason::Private));
+    }
+
+    #[test]
+    fn parse_newer_version_test() {
+        let gnd = r#"There are newer versions of this gallery available:<br><br><a href="https://e-hentai.org/g/2062068/4f9a3c81d2/">Title v2</a>, added 2021-11-28 10:12<br><a href="https://e-hentai.org/g/2062069/5e8b4d92e3/">Title v3</a>, added 2022-01-02 03:04<br>"#;
+
+        let version_vec = parse_newer_version(gnd).unwrap();
+        assert_eq!(version_vec.len(), 2);
+        assert_eq!(version_vec[0].identity.gid, 2062068);
+        assert_eq!(version_vec[0].title, "Title v2");
+        assert_eq!(version_vec[1].posted, parse_posted("2022-01-02 03:04").unwrap());
+        assert_eq!(version_vec[1].posted.timestamp(), 1641092640);
     }
 
     #[test]
@@ -808,6 +1021,10 @@ mod tests {
 
         assert_eq!(parse_large(ele).is_ok(), true);
 
+        let page_url_vec = GalleryPreviewSet::Large(parse_large(ele).unwrap()).page_url_vec().unwrap();
+        assert_eq!(page_url_vec.len(), 2);
+        assert_eq!(page_url_vec[1].p_token, "ad7a3b7014");
+
         let ele = r#"
             <div id="gdt">
                 <div class="gdtm" style="height:167px">
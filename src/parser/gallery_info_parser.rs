@@ -2,8 +2,8 @@ use std::str::FromStr;
 use regex::Regex;
 use visdom::Vis;
 use crate::{
-    parser::{ATTRIBUTE_NOT_FOUND, ParseError, REGEX_MATCH_FAILED},
-    structures::{Category, FavoriteSlot, GalleryIdentity, GalleryInfo, Rating, Thumb},
+    parser::{ATTRIBUTE_NOT_FOUND, ParseError, REGEX_MATCH_FAILED, parse_posted},
+    structures::{Category, FavoriteSlot, GalleryIdentity, GalleryInfo, LanguageDetection, ParsedTitle, Rating, Thumb},
 };
 
 impl FromStr for GalleryInfo {
@@ -73,7 +73,7 @@ impl FromStr for GalleryInfo {
 
         // 7. posted
         let div = root.find("[id^=posted_]");
-        let posted = div.text();
+        let posted = parse_posted(&div.text())?;
 
         // 8. is_favorited
         let is_favorited = div.attr("style").is_some();
@@ -107,33 +107,9 @@ impl FromStr for GalleryInfo {
         let div = root.find(r#".glink"#);
         let title = div.text();
 
-        // 13. simple_language_opt
-        let simple_language_opt = if let Some(ref simple_tag_vec) = simple_tag_vec_opt {
-            // Compact Extended
-            let mut simple_language_opt = None;
-            for tag in simple_tag_vec {
-                let idx_opt = S_LANG_TAGS.iter().position(|&t| t == tag);
-                if let Some(idx) = idx_opt {
-                    simple_language_opt = Some(String::from(S_LANGS[idx]));
-                    break;
-                }
-            }
-            simple_language_opt
-        } else {
-            // Minimal MinimalPlus Thumbnail
-            let link = root.find(r#".glink"#);
-            let idx_opt = S_LANG_PATTERNS.iter().position(|pattern| {
-                let regex = Regex::new(pattern).unwrap();
-                regex.is_match(&link.text())
-            });
-
-            let mut simple_language_opt = None;
-            if let Some(idx) = idx_opt {
-                simple_language_opt = Some(String::from(S_LANGS[idx]));
-            }
-
-            simple_language_opt
-        };
+        // 13. language_opt
+        let simple_tag_vec = simple_tag_vec_opt.as_deref().unwrap_or_default();
+        let language_opt = LanguageDetection::detect(simple_tag_vec, &title, None);
 
         Ok(GalleryInfo {
             identity,
@@ -145,7 +121,7 @@ impl FromStr for GalleryInfo {
             simple_tag_vec_opt,
             rating,
             pages,
-            simple_language_opt,
+            language_opt,
             is_favorited,
             favorite_slot_opt,
             favorite_name_opt,
@@ -153,74 +129,12 @@ impl FromStr for GalleryInfo {
     }
 }
 
-const PATTERN_PAGES: &str = r#"(\d+) page"#;
+impl GalleryInfo {
+    pub fn parsed_title(&self) -> ParsedTitle {
+        self.title.parse::<ParsedTitle>().unwrap_or_default()
+    }
+}
 
-const S_LANGS: [&str; 14] = [
-    "S_LANG_EN",
-    "S_LANG_ZH",
-    "S_LANG_ES",
-    "S_LANG_KO",
-    "S_LANG_RU",
-    "S_LANG_FR",
-    "S_LANG_PT",
-    "S_LANG_TH",
-    "S_LANG_DE",
-    "S_LANG_IT",
-    "S_LANG_VI",
-    "S_LANG_PL",
-    "S_LANG_HU",
-    "S_LANG_NL",
-];
-
-const S_LANG_PATTERNS: [&str; 14] = [
-    r#"[(\[]eng(?:lish)?[)\]]|英訳"#,
-    // r#[(（\[]ch(?:inese)?[)）\]]|[汉漢]化|中[国國][语語]|中文|中国翻訳#,
-    r#"[(\uFF08\[]ch(?:inese)?[)\uFF09\]]|[汉漢]化|中[国國][语語]|中文|中国翻訳"#,
-    r#"[(\[]spanish[)\]]|[(\[]Español[)\]]|スペイン翻訳"#,
-    r#"[(\[]korean?[)\]]|韓国翻訳"#,
-    r#"[(\[]rus(?:sian)?[)\]]|ロシア翻訳"#,
-    r#"[(\[]fr(?:ench)?[)\]]|フランス翻訳"#,
-    r#"[(\[]portuguese|ポルトガル翻訳"#,
-    r#"[(\[]thai(?: ภาษาไทย)?[)\]]|แปลไทย|タイ翻訳"#,
-    r#"[(\[]german[)\]]|ドイツ翻訳"#,
-    r#"[(\[]italiano?[)\]]|イタリア翻訳"#,
-    r#"[(\[]vietnamese(?: Tiếng Việt)?[)\]]|ベトナム翻訳"#,
-    r#"[(\[]polish[)\]]|ポーランド翻訳"#,
-    r#"[(\[]hun(?:garian)?[)\]]|ハンガリー翻訳"#,
-    r#"[(\[]dutch[)\]]|オランダ翻訳"#,
-];
-
-const S_LANG_TAGS: [&str; 14] = [
-    "language:english",
-    "language:chinese",
-    "language:spanish",
-    "language:korean",
-    "language:russian",
-    "language:french",
-    "language:portuguese",
-    "language:thai",
-    "language:german",
-    "language:italian",
-    "language:vietnamese",
-    "language:polish",
-    "language:hungarian",
-    "language:dutch",
-];
+const PATTERN_PAGES: &str = r#"(\d+) page"#;
 
 // const PATTERN_THUMB_SIZE: &str = r#"height:(\d+)px;width:(\d+)px"#;
-
-// const S_LANG_JA: &str = "JA";
-// const S_LANG_EN: &str = "EN";
-// const S_LANG_ZH: &str = "ZH";
-// const S_LANG_NL: &str = "NL";
-// const S_LANG_FR: &str = "FR";
-// const S_LANG_DE: &str = "DE";
-// const S_LANG_HU: &str = "HU";
-// const S_LANG_IT: &str = "IT";
-// const S_LANG_KO: &str = "KO";
-// const S_LANG_PL: &str = "PL";
-// const S_LANG_PT: &str = "PT";
-// const S_LANG_RU: &str = "RU";
-// const S_LANG_ES: &str = "ES";
-// const S_LANG_TH: &str = "TH";
-// const S_LANG_VI: &str = "VI";
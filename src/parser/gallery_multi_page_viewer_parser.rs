@@ -0,0 +1,189 @@
+use std::str::FromStr;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use crate::{
+    parser::{IMAGE_QUOTA_EXCEEDED, ParseError, REGEX_MATCH_FAILED, is_quota_exceeded_image, parse_file_size, gallery_page_parser::parse_origin_image_size},
+    structures::{GalleryMultiPageViewer, ImageSize, MultiPageViewerDispatch, MultiPageViewerImage},
+};
+
+impl FromStr for GalleryMultiPageViewer {
+    type Err = ParseError;
+
+    /// ```html
+    /// <script type="text/javascript">
+    /// var gid = 2062874;
+    /// var mpvkey = "dd2ea4a2e8";
+    /// var pagecount = 24;
+    /// var imagelist = [{"n":"01.jpg","k":"35142216f7","t":"(https:\/\/ehgt.org\/...) -0px 0"}, ...];
+    /// </script>
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let regex = Regex::new(PATTERN_GID).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let gid = captures[1].parse::<u64>()?;
+
+        let regex = Regex::new(PATTERN_MPV_KEY).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let mpv_key = String::from(&captures[1]);
+
+        let regex = Regex::new(PATTERN_PAGE_COUNT).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let pages = captures[1].parse::<u32>()?;
+
+        let regex = Regex::new(PATTERN_IMAGE_LIST).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let image_vec = serde_json::from_str::<Vec<MultiPageViewerImageInternal>>(&captures[1])?
+            .into_iter()
+            .map(|image| MultiPageViewerImage {
+                filename: image.n,
+                p_token: image.k,
+                thumb: image.t,
+            })
+            .collect();
+
+        Ok(GalleryMultiPageViewer {
+            gid,
+            mpv_key,
+            pages,
+            image_vec,
+        })
+    }
+}
+
+impl FromStr for MultiPageViewerDispatch {
+    type Err = ParseError;
+
+    /// ```json
+    /// {
+    ///     "d": "1280 x 1810 :: 345.2 KiB",
+    ///     "o": "Download original 2458 x 3497 2.39 MiB source",
+    ///     "lf": "fullimg/2062874/2/1ab2c3d4e5f/02.jpg",
+    ///     "i": "https://abc.hath.network/h/xxxx/02.jpg",
+    ///     "s": "43455-456789"
+    /// }
+    /// ```
+    /// `lf` is relative to the site, `EhClient::mpv_dispatch` resolves it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let internal = serde_json::from_str::<MultiPageViewerDispatchInternal>(s)?;
+        if let Some(error) = internal.error {
+            return Err(ParseError::FromServer(error));
+        }
+
+        if is_quota_exceeded_image(&internal.i) {
+            return Err(IMAGE_QUOTA_EXCEEDED);
+        }
+
+        let regex = Regex::new(PATTERN_DISPATCH_SIZE).unwrap();
+        let captures = regex.captures(&internal.d).ok_or(REGEX_MATCH_FAILED)?;
+        let image_size = ImageSize {
+            width: captures[1].parse::<u32>()?,
+            height: captures[2].parse::<u32>()?,
+            file_size: parse_file_size(&captures[3])?,
+        };
+
+        let origin_image_size_opt = parse_origin_image_size(&internal.o)?;
+        let origin_image_url_opt = origin_image_size_opt.and(internal.lf);
+
+        Ok(MultiPageViewerDispatch {
+            image_url: internal.i,
+            skip_hath_key: internal.s,
+            origin_image_url_opt,
+            image_size,
+            origin_image_size_opt,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MultiPageViewerImageInternal {
+    n: String,
+    k: String,
+    #[serde(default)]
+    t: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MultiPageViewerDispatchInternal {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    d: String,
+    #[serde(default)]
+    o: String,
+    #[serde(default)]
+    lf: Option<String>,
+    #[serde(default)]
+    i: String,
+    #[serde(default)]
+    s: String,
+}
+
+const PATTERN_GID: &str = r"var gid\s*=\s*(\d+);";
+const PATTERN_MPV_KEY: &str = r#"var mpvkey\s*=\s*"([0-9a-z]+)";"#;
+const PATTERN_PAGE_COUNT: &str = r"var pagecount\s*=\s*(\d+);";
+const PATTERN_IMAGE_LIST: &str = r"var imagelist\s*=\s*(\[.*?\]);";
+const PATTERN_DISPATCH_SIZE: &str = r"(\d+) x (\d+) :: ([\d.]+ ?[KMGT]?i?B)";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let s = r#"
+            <script type="text/javascript">
+            var base_url = "https://e-hentai.org/";
+            var gid = 2062874;
+            var mpvkey = "dd2ea4a2e8";
+            var pagecount = 2;
+            var api_url = "https://api.e-hentai.org/api.php";
+            var imagelist = [{"n":"01.jpg","k":"35142216f7","t":"(https:\/\/ehgt.org\/m\/002062\/2062874-00.jpg) -0px 0"},{"n":"02.jpg","k":"7b0a1c2d3e","t":"(https:\/\/ehgt.org\/m\/002062\/2062874-00.jpg) -200px 0"}];
+            </script>
+        "#;
+
+        let mpv = s.parse::<GalleryMultiPageViewer>().unwrap();
+        assert_eq!(mpv.gid, 2062874);
+        assert_eq!(mpv.mpv_key, "dd2ea4a2e8");
+        assert_eq!(mpv.pages, 2);
+        assert_eq!(mpv.image_vec[1], MultiPageViewerImage {
+            filename: String::from("02.jpg"),
+            p_token: String::from("7b0a1c2d3e"),
+            thumb: String::from("(https://ehgt.org/m/002062/2062874-00.jpg) -200px 0"),
+        });
+
+        let page_url_vec = mpv.page_url_vec();
+        assert_eq!((page_url_vec[1].page, page_url_vec[1].p_token.as_str()), (1, "7b0a1c2d3e"));
+    }
+
+    #[test]
+    fn parse_dispatch_test() {
+        let json = r#"
+            {
+                "d": "1280 x 1810 :: 345.2 KiB",
+                "o": "Download original 2458 x 3497 2.39 MiB source",
+                "lf": "fullimg/2062874/2/1ab2c3d4e5f/02.jpg",
+                "ls": "?f_shash=abcdef&fs_from=02.jpg",
+                "lo": "s/7b0a1c2d3e/2062874-2",
+                "xres": "1280",
+                "yres": "1810",
+                "i": "https://abc.hath.network/h/xxxx/02.jpg",
+                "s": "43455-456789"
+            }
+        "#;
+
+        let dispatch = json.parse::<MultiPageViewerDispatch>().unwrap();
+        assert_eq!(dispatch, MultiPageViewerDispatch {
+            image_url: String::from("https://abc.hath.network/h/xxxx/02.jpg"),
+            skip_hath_key: String::from("43455-456789"),
+            origin_image_url_opt: Some(String::from("fullimg/2062874/2/1ab2c3d4e5f/02.jpg")),
+            image_size: ImageSize { width: 1280, height: 1810, file_size: 353485 },
+            origin_image_size_opt: Some(ImageSize { width: 2458, height: 3497, file_size: 2506097 }),
+        });
+
+        let json = r#"{"d": "1280 x 1810 :: 345.2 KiB", "i": "https://ehgt.org/g/509.gif", "s": "43455-456789"}"#;
+        assert!(matches!(json.parse::<MultiPageViewerDispatch>(), Err(ParseError::ImageQuotaExceeded)));
+
+        let json = r#"{"error": "Key mismatch"}"#;
+        assert!(matches!(json.parse::<MultiPageViewerDispatch>(), Err(ParseError::FromServer(_))));
+    }
+}
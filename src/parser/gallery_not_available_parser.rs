@@ -1,19 +1,87 @@
 use std::str::FromStr;
+use regex::Regex;
 use visdom::Vis;
-use crate::{parser::ParseError, structures::GalleryNotAvailable};
+use crate::{parser::{ParseError, DOM_NOT_FOUND}, structures::{GalleryNotAvailable, GalleryUnavailable}};
 
 impl FromStr for GalleryNotAvailable {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let root = Vis::load(s)?;
-        let p = root.find(".d p:first-child");
-        let error = p.text();
+        let p = root.find(".d p");
+        if p.is_empty() {
+            return Err(DOM_NOT_FOUND);
+        }
 
-        Ok(GalleryNotAvailable { error })
+        let error = p.first().text();
+        let mut kind = error.parse::<GalleryUnavailable>()?;
+
+        // The reason of an expunged gallery follows in the next paragraph.
+        if let GalleryUnavailable::Expunged { reason_opt: None } = kind {
+            let reason = p.eq(1).text();
+            let reason = reason.trim().trim_start_matches("Reason:").trim();
+            if !reason.is_empty() {
+                kind = GalleryUnavailable::Expunged { reason_opt: Some(String::from(reason)) };
+            }
+        }
+
+        Ok(GalleryNotAvailable { error, kind })
     }
 }
 
+impl FromStr for GalleryUnavailable {
+    type Err = ParseError;
+
+    /// Classify an error message of the gallery page or of the gdata API.
+    ///
+    /// ```text
+    /// This gallery has been removed or is unavailable.
+    /// This gallery is unavailable due to a copyright claim by Foo.
+    /// Key missing, or incorrect key provided.
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let lower = s.to_lowercase();
+
+        let regex = Regex::new(PATTERN_COPYRIGHT).unwrap();
+        if let Some(cap) = regex.captures(s) {
+            return Ok(GalleryUnavailable::CopyrightClaim { claimant: String::from(cap[1].trim()) });
+        }
+
+        let regex = Regex::new(PATTERN_EXPUNGED).unwrap();
+        if let Some(cap) = regex.captures(s) {
+            let reason_opt = cap.get(1).map(|m| String::from(m.as_str().trim()));
+            return Ok(GalleryUnavailable::Expunged { reason_opt });
+        }
+
+        let kind = if lower.contains(PINING_STRING) {
+            GalleryUnavailable::Expunged { reason_opt: None }
+        } else if lower.contains(INVALID_KEY_STRING) {
+            GalleryUnavailable::InvalidKey
+        } else if lower.contains(NOT_FOUND_STRING) {
+            GalleryUnavailable::NotFound
+        } else if lower.contains(EXHENTAI_STRING) {
+            GalleryUnavailable::ExhentaiOnly
+        } else if lower.contains(REMOVED_STRING) {
+            GalleryUnavailable::Removed
+        } else {
+            GalleryUnavailable::Other(String::from(s))
+        };
+
+        Ok(kind)
+    }
+}
+
+const PATTERN_COPYRIGHT: &str = r"(?i)copyright claim by (.+?)\.?$";
+const PATTERN_EXPUNGED: &str = r"(?i)has been expunged\.?(?:\s*Reason:\s*(.+))?";
+
+// Compared against the lowercase message.
+const PINING_STRING: &str = "pining for the fjords";
+const INVALID_KEY_STRING: &str = "incorrect key";
+const NOT_FOUND_STRING: &str = "not found";
+const EXHENTAI_STRING: &str = "exhentai";
+const REMOVED_STRING: &str = "removed";
+
 #[cfg(test)]
 mod tests {
     use crate::test_helper::read_test_file;
@@ -23,7 +91,30 @@ mod tests {
     fn parse_test() {
         let s = read_test_file("gallery_not_available.html");
         assert_eq!(s.parse::<GalleryNotAvailable>().unwrap(), GalleryNotAvailable {
-            error: String::from("This gallery_list has been removed or is unavailable.")
+            error: String::from("This gallery_list has been removed or is unavailable."),
+            kind: GalleryUnavailable::Removed,
         })
     }
+
+    #[test]
+    fn parse_kind_test() {
+        let s = "This gallery is unavailable due to a copyright claim by Some Publisher.";
+        assert_eq!(s.parse::<GalleryUnavailable>().unwrap(), GalleryUnavailable::CopyrightClaim {
+            claimant: String::from("Some Publisher")
+        });
+
+        let s = "Key missing, or incorrect key provided.";
+        assert_eq!(s.parse::<GalleryUnavailable>().unwrap(), GalleryUnavailable::InvalidKey);
+
+        let s = "Gallery not found. If you just added this gallery, you might have to wait a short while before it becomes available.";
+        assert_eq!(s.parse::<GalleryUnavailable>().unwrap(), GalleryUnavailable::NotFound);
+
+        let s = "This gallery is pining for the fjords.";
+        assert_eq!(s.parse::<GalleryUnavailable>().unwrap(), GalleryUnavailable::Expunged { reason_opt: None });
+
+        let ele = r#"<div class="d"><p>This gallery has been expunged.</p><p>Reason: duplicate of 2062067</p></div>"#;
+        assert_eq!(ele.parse::<GalleryNotAvailable>().unwrap().kind, GalleryUnavailable::Expunged {
+            reason_opt: Some(String::from("duplicate of 2062067"))
+        });
+    }
 }
@@ -1,7 +1,17 @@
 use std::str::FromStr;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use crate::{parser::{ParseError, REGEX_MATCH_FAILED, unescape::unescape}, structures::GalleryPageApi};
+use crate::{
+    parser::{
+        IMAGE_QUOTA_EXCEEDED, ParseError, REGEX_MATCH_FAILED, is_quota_exceeded_image,
+        gallery_page_parser::{
+            PATTERN_IMAGE_URL, PATTERN_ORIGIN_IMAGE_URL, PATTERN_SKIP_HATH_KEY,
+            parse_image_info, parse_navigation, parse_origin_image_size,
+        },
+        unescape::unescape,
+    },
+    structures::GalleryPageApi,
+};
 
 impl FromStr for GalleryPageApi {
     type Err = ParseError;
@@ -12,39 +22,73 @@ impl FromStr for GalleryPageApi {
         let regex = Regex::new(PATTERN_IMAGE_URL).unwrap();
         let captures = regex.captures(&internal.i3).ok_or(REGEX_MATCH_FAILED)?;
         let image_url = String::from(&captures[1]);
+        if is_quota_exceeded_image(&image_url) {
+            return Err(IMAGE_QUOTA_EXCEEDED);
+        }
 
         let regex = Regex::new(PATTERN_SKIP_HATH_KEY).unwrap();
         let captures = regex.captures(&internal.i6).ok_or(REGEX_MATCH_FAILED)?;
         let skip_hath_key = String::from(&captures[1]);
 
         let regex = Regex::new(PATTERN_ORIGIN_IMAGE_URL).unwrap();
-        let captures = regex.captures(&internal.i7).ok_or(REGEX_MATCH_FAILED)?;
-        let origin_image_url = format!("{}{}{}", &captures[1], r#"fullimg.php"#, unescape(&captures[2]));
+        let origin_image_url_opt = regex.captures(&internal.i7)
+            .map(|captures| format!("{}{}{}", &captures[1], r#"fullimg.php"#, unescape(&captures[2])));
+
+        let (filename, image_size) = parse_image_info(&internal.i)?;
+        let origin_image_size_opt = parse_origin_image_size(&internal.i7)?;
+        let navigation_opt = parse_navigation(&internal.n);
 
         Ok(GalleryPageApi {
             image_url,
             skip_hath_key,
-            origin_image_url,
+            origin_image_url_opt,
+            filename,
+            image_size,
+            origin_image_size_opt,
+            navigation_opt,
         })
     }
 }
 
-const PATTERN_IMAGE_URL: &str = r#"<img[^>]*src="([^"]+)" style"#;
-const PATTERN_SKIP_HATH_KEY: &str = r#"onclick="return nl\('([^\)]+)'\)"#;
-const PATTERN_ORIGIN_IMAGE_URL: &str = r#"<a href="([^"]+)fullimg.php([^"]+)">"#;
-
 #[derive(Debug, Serialize, Deserialize)]
 struct GalleryPageApiInternal {
+    i: String,
+    n: String,
     i3: String,
     i6: String,
+    #[serde(default)]
     i7: String,
 }
 
 
 #[cfg(test)]
 mod tests {
+    use crate::structures::ImageSize;
     use super::*;
 
     #[test]
-    fn parse_test() {}
+    fn parse_test() {
+        let json = r##"
+            {
+                "p": 2,
+                "s": "s/ad7a3b7014/1496103-2",
+                "n": "<div class=\"sn\"><a onclick=\"return load_image(1, '5bf9580b3b')\" href=\"https://e-hentai.org/s/5bf9580b3b/1496103-1\"><img src=\"https://ehgt.org/g/f.png\" /></a><a id=\"prev\" onclick=\"return load_image(1, '5bf9580b3b')\" href=\"https://e-hentai.org/s/5bf9580b3b/1496103-1\"><img src=\"https://ehgt.org/g/p.png\" /></a><div><span>2</span> / <span>75</span></div><a id=\"next\" onclick=\"return load_image(3, '7b0a1c2d3e')\" href=\"https://e-hentai.org/s/7b0a1c2d3e/1496103-3\"><img src=\"https://ehgt.org/g/n.png\" /></a><a onclick=\"return load_image(75, '9c8d7e6f5a')\" href=\"https://e-hentai.org/s/9c8d7e6f5a/1496103-75\"><img src=\"https://ehgt.org/g/l.png\" /></a></div>",
+                "i": "<div>02.jpg :: 1280 x 1810 :: 345.2 KiB</div>",
+                "i3": "<a onclick=\"return load_image(3, '7b0a1c2d3e')\" href=\"https://e-hentai.org/s/7b0a1c2d3e/1496103-3\"><img id=\"img\" src=\"https://abc.hath.network/h/xxxx/02.jpg\" style=\"height:1810px;width:1280px\" /></a>",
+                "i6": " &nbsp; <a href=\"#\" id=\"loadfail\" onclick=\"return nl('43455-456789')\">Reload broken image</a>",
+                "i7": "",
+                "x": "1280",
+                "y": "1810"
+            }
+        "##;
+
+        let page_api = json.parse::<GalleryPageApi>().unwrap();
+        assert_eq!(page_api.image_url, "https://abc.hath.network/h/xxxx/02.jpg");
+        assert_eq!(page_api.skip_hath_key, "43455-456789");
+        assert_eq!(page_api.origin_image_url_opt, None);
+        assert_eq!(page_api.filename, "02.jpg");
+        assert_eq!(page_api.image_size, ImageSize { width: 1280, height: 1810, file_size: 353485 });
+        assert_eq!(page_api.origin_image_size_opt, None);
+        assert_eq!(page_api.navigation_opt.map(|navigation| navigation.page), Some(1));
+    }
 }
@@ -1,6 +1,10 @@
 use std::str::FromStr;
 use regex::Regex;
-use crate::{parser::{ParseError, REGEX_MATCH_FAILED, unescape::unescape}, structures::GalleryPage};
+use visdom::Vis;
+use crate::{
+    parser::{IMAGE_QUOTA_EXCEEDED, ParseError, REGEX_MATCH_FAILED, is_quota_exceeded_image, parse_file_size, unescape::unescape},
+    structures::{GalleryPage, GalleryPageUrl, ImageSize, PageNavigation},
+};
 
 impl FromStr for GalleryPage {
     type Err = ParseError;
@@ -9,31 +13,123 @@ impl FromStr for GalleryPage {
         let regex = Regex::new(PATTERN_IMAGE_URL).unwrap();
         let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
         let image_url = String::from(&captures[1]);
+        if is_quota_exceeded_image(&image_url) {
+            return Err(IMAGE_QUOTA_EXCEEDED);
+        }
 
         let regex = Regex::new(PATTERN_SKIP_HATH_KEY).unwrap();
         let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
         let skip_hath_key = String::from(&captures[1]);
 
         let regex = Regex::new(PATTERN_ORIGIN_IMAGE_URL).unwrap();
-        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
-        let origin_image_url = format!("{}{}{}", &captures[1], r#"fullimg.php"#, unescape(&captures[2]));
+        let origin_image_url_opt = regex.captures(s)
+            .map(|captures| format!("{}{}{}", &captures[1], r#"fullimg.php"#, unescape(&captures[2])));
 
         let regex = Regex::new(PATTERN_SHOW_KEY).unwrap();
         let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
         let show_key = String::from(&captures[1]);
 
+        let (filename, image_size) = parse_image_info(s)?;
+        let origin_image_size_opt = parse_origin_image_size(s)?;
+        let navigation_opt = parse_navigation(s);
+
         Ok(GalleryPage {
             image_url,
             skip_hath_key,
-            origin_image_url,
+            origin_image_url_opt,
             show_key,
+            filename,
+            image_size,
+            origin_image_size_opt,
+            navigation_opt,
         })
     }
 }
 
-const PATTERN_IMAGE_URL: &str = r#"<img[^>]*src="([^"]+)" style"#;
-const PATTERN_SKIP_HATH_KEY: &str = r#"onclick="return nl\('([^\)]+)'\)"#;
-const PATTERN_ORIGIN_IMAGE_URL: &str = r#"<a href=\"([^\"]+)fullimg.php([^\"]+)\">"#;
+impl GalleryPage {
+    /// The extension of `filename`, in lowercase.
+    pub fn extension_opt(&self) -> Option<String> {
+        let (_, extension) = self.filename.rsplit_once('.')?;
+        Some(extension.to_lowercase())
+    }
+}
+
+/// ```html
+/// <div>02.jpg :: 1280 x 1807 :: 386.4 KiB</div>
+/// ```
+pub(super) fn parse_image_info(s: &str) -> Result<(String, ImageSize), ParseError> {
+    let regex = Regex::new(PATTERN_IMAGE_INFO).unwrap();
+    let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+
+    let filename = String::from(unescape(captures[1].trim()));
+    let width = captures[2].parse::<u32>()?;
+    let height = captures[3].parse::<u32>()?;
+    let file_size = parse_file_size(&captures[4])?;
+
+    Ok((filename, ImageSize { width, height, file_size }))
+}
+
+/// ```html
+/// <a href="https://e-hentai.org/fullimg.php?gid=...">Download original 2458 x 3497 2.39 MiB source</a>
+/// ```
+pub(super) fn parse_origin_image_size(s: &str) -> Result<Option<ImageSize>, ParseError> {
+    let regex = Regex::new(PATTERN_ORIGIN_IMAGE_SIZE).unwrap();
+    let Some(captures) = regex.captures(s) else {
+        return Ok(None);
+    };
+
+    let width = captures[1].parse::<u32>()?;
+    let height = captures[2].parse::<u32>()?;
+    let file_size = parse_file_size(&captures[3])?;
+
+    Ok(Some(ImageSize { width, height, file_size }))
+}
+
+/// ```html
+/// <div class="sn">
+///     <a href="https://e-hentai.org/s/f6e1b2c3d4/2062874-1"><img src="https://ehgt.org/g/f.png" /></a>
+///     <a id="prev" href="https://e-hentai.org/s/f6e1b2c3d4/2062874-1"><img src="https://ehgt.org/g/p.png" /></a>
+///     <div><span>2</span> / <span>24</span></div>
+///     <a id="next" href="https://e-hentai.org/s/a1b2c3d4e5/2062874-3"><img src="https://ehgt.org/g/n.png" /></a>
+///     <a href="https://e-hentai.org/s/b2c3d4e5f6/2062874-24"><img src="https://ehgt.org/g/l.png" /></a>
+/// </div>
+/// ```
+///
+/// `None` if the links are missing or not in this shape, the image can be
+/// downloaded without them.
+pub(super) fn parse_navigation(s: &str) -> Option<PageNavigation> {
+    let root = Vis::load(s).ok()?;
+    let sn = root.find(".sn").first();
+
+    let page_url_vec = sn.children("a")
+        .into_iter()
+        .map(|a| a.get_attribute("href")?.to_string().parse::<GalleryPageUrl>().ok())
+        .collect::<Option<Vec<GalleryPageUrl>>>()?;
+
+    let span_vec = sn.find("span")
+        .into_iter()
+        .map(|span| span.text().parse::<u32>().ok())
+        .collect::<Option<Vec<u32>>>()?;
+
+    let (Ok([first, prev, next, last]), [page, pages]) = (<[GalleryPageUrl; 4]>::try_from(page_url_vec), span_vec.as_slice()) else {
+        return None;
+    };
+
+    Some(PageNavigation {
+        page: page.saturating_sub(1),
+        pages: *pages,
+        first,
+        prev,
+        next,
+        last,
+    })
+}
+
+pub(super) const PATTERN_IMAGE_URL: &str = r#"<img[^>]*src="([^"]+)" style"#;
+pub(super) const PATTERN_SKIP_HATH_KEY: &str = r#"onclick="return nl\('([^\)]+)'\)"#;
+pub(super) const PATTERN_ORIGIN_IMAGE_URL: &str = r#"<a href="([^"]+)fullimg.php([^"]+)">"#;
+const PATTERN_IMAGE_INFO: &str = r#"<div>([^<>]+?) :: (\d+) x (\d+) :: ([\d.]+ ?[KMGT]?i?B)</div>"#;
+const PATTERN_ORIGIN_IMAGE_SIZE: &str = r#"Download original (\d+) x (\d+) ([\d.]+ ?[KMGT]?i?B)"#;
 // TODO Not sure about the size of show keys
 const PATTERN_SHOW_KEY: &str = r#"var showkey="([0-9a-z]+)";"#;
 
@@ -42,5 +138,37 @@ mod tests {
     use super::*;
 
     #[test]
-    fn parse_test() {}
+    fn parse_test() {
+        let s = r##"
+            <script type="text/javascript">var showkey="e1a2b3c4d5f";</script>
+            <div id="i2"><div class="sn"><a onclick="return load_image(1, '5bf9580b3b')" href="https://e-hentai.org/s/5bf9580b3b/1496103-1"><img src="https://ehgt.org/g/f.png" /></a><a id="prev" onclick="return load_image(1, '5bf9580b3b')" href="https://e-hentai.org/s/5bf9580b3b/1496103-1"><img src="https://ehgt.org/g/p.png" /></a><div><span>2</span> / <span>75</span></div><a id="next" onclick="return load_image(3, '7b0a1c2d3e')" href="https://e-hentai.org/s/7b0a1c2d3e/1496103-3"><img src="https://ehgt.org/g/n.png" /></a><a onclick="return load_image(75, '9c8d7e6f5a')" href="https://e-hentai.org/s/9c8d7e6f5a/1496103-75"><img src="https://ehgt.org/g/l.png" /></a></div><div>AnMMSC_2_001_2.JPG :: 1280 x 1810 :: 345.2 KiB</div></div>
+            <div id="i3"><a onclick="return load_image(2, 'ad7a3b7014')" href="https://e-hentai.org/s/ad7a3b7014/1496103-2"><img id="img" src="https://abc.hath.network/h/xxxx/keystamp=1;fileindex=2;xres=1280/01.jpg" style="height:1810px;width:1280px" onerror="this.onerror=null; nl('43455-456789')" /></a></div>
+            <div id="i6"><a href="#" id="loadfail" onclick="return nl('43455-456789')">Reload broken image</a></div>
+            <div id="i7"><a href="https://e-hentai.org/fullimg.php?gid=1496103&amp;page=2&amp;key=xxxx">Download original 2458 x 3497 2.39 MiB source</a></div>
+        "##;
+
+        let page = s.parse::<GalleryPage>().unwrap();
+        assert_eq!(page.image_url, "https://abc.hath.network/h/xxxx/keystamp=1;fileindex=2;xres=1280/01.jpg");
+        assert_eq!(page.skip_hath_key, "43455-456789");
+        assert_eq!(page.origin_image_url_opt.as_deref(), Some("https://e-hentai.org/fullimg.php?gid=1496103&page=2&key=xxxx"));
+        assert_eq!(page.show_key, "e1a2b3c4d5f");
+        assert_eq!(page.filename, "AnMMSC_2_001_2.JPG");
+        assert_eq!(page.extension_opt().as_deref(), Some("jpg"));
+        assert_eq!(page.image_size, ImageSize { width: 1280, height: 1810, file_size: 353485 });
+        assert_eq!(page.origin_image_size_opt, Some(ImageSize { width: 2458, height: 3497, file_size: 2506097 }));
+
+        let navigation = page.navigation_opt.unwrap();
+        assert_eq!((navigation.page, navigation.pages), (1, 75));
+        assert_eq!(navigation.prev.page, 0);
+        assert_eq!(navigation.next.p_token, "7b0a1c2d3e");
+        assert_eq!(navigation.last.page, 74);
+
+        let without_last = s.replace(r#"<a onclick="return load_image(75, '9c8d7e6f5a')" href="https://e-hentai.org/s/9c8d7e6f5a/1496103-75"><img src="https://ehgt.org/g/l.png" /></a>"#, "");
+        let page = without_last.parse::<GalleryPage>().unwrap();
+        assert_eq!(page.navigation_opt, None);
+        assert_eq!(page.filename, "AnMMSC_2_001_2.JPG");
+
+        let s = s.replace("https://abc.hath.network/h/xxxx/keystamp=1;fileindex=2;xres=1280/01.jpg", "https://ehgt.org/g/509.gif");
+        assert!(matches!(s.parse::<GalleryPage>(), Err(ParseError::ImageQuotaExceeded)));
+    }
 }
@@ -0,0 +1,74 @@
+use std::str::FromStr;
+use regex::Regex;
+use crate::{parser::{ParseError, REGEX_MATCH_FAILED, SIGN_IN_REQUIRED}, structures::{ImageLimits, ResetCost}};
+
+impl FromStr for ImageLimits {
+    type Err = ParseError;
+
+    /// ```html
+    /// <div class="homebox">
+    ///     <p>You are currently at <strong>1,234</strong> towards a limit of <strong>5,000</strong>.</p>
+    ///     <p>Reset Cost: <strong>6</strong> GP</p>
+    /// </div>
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(SIGN_IN_REQUIRED_STRING) {
+            return Err(SIGN_IN_REQUIRED);
+        }
+
+        let regex = Regex::new(PATTERN_LIMITS).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let current = captures[1].replace(',', "").parse::<u32>()?;
+        let maximum = captures[2].replace(',', "").parse::<u32>()?;
+
+        let regex = Regex::new(PATTERN_RESET_COST).unwrap();
+        let reset_cost_opt = match regex.captures(s) {
+            Some(captures) => {
+                let cost = captures[1].replace(',', "").parse::<u64>()?;
+                Some(match &captures[2] {
+                    "GP" => ResetCost::Gp(cost),
+                    _ => ResetCost::Credits(cost),
+                })
+            }
+            None => None,
+        };
+
+        Ok(ImageLimits {
+            current,
+            maximum,
+            reset_cost_opt,
+        })
+    }
+}
+
+const SIGN_IN_REQUIRED_STRING: &str = "This page requires you to log on.";
+const PATTERN_LIMITS: &str = r"You are currently at <strong>([\d,]+)</strong> towards (?:a|your account) limit of <strong>([\d,]+)</strong>";
+const PATTERN_RESET_COST: &str = r"Reset Cost: <strong>([\d,]+)</strong> (GP|Credits)";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let s = r#"
+            <div class="homebox">
+                <p>You are currently at <strong>1,234</strong> towards a limit of <strong>5,000</strong>.</p>
+                <p>This regenerates at a rate of <strong>3</strong> per minute.</p>
+                <p>Reset Cost: <strong>6</strong> GP</p>
+            </div>
+        "#;
+
+        let limits = s.parse::<ImageLimits>().unwrap();
+        assert_eq!(limits, ImageLimits {
+            current: 1234,
+            maximum: 5000,
+            reset_cost_opt: Some(ResetCost::Gp(6)),
+        });
+        assert_eq!(limits.remaining(), 3766);
+        assert!(!limits.is_exceeded());
+
+        let s = r#"<p>This page requires you to log on.</p>"#;
+        assert!(matches!(s.parse::<ImageLimits>(), Err(ParseError::SignInRequired)));
+    }
+}
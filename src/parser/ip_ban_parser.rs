@@ -0,0 +1,60 @@
+use std::str::FromStr;
+use std::time::Duration;
+use regex::Regex;
+use crate::{parser::{ParseError, REGEX_MATCH_FAILED}, structures::IpBan};
+
+impl FromStr for IpBan {
+    type Err = ParseError;
+
+    /// ```text
+    /// Your IP address has been temporarily banned for excessive pageloads which indicates
+    /// that you are using automated mirroring/harvesting software. The ban expires in 23 minutes and 12 seconds
+    /// ```
+    /// The ban page is that plain text alone, a html page quoting it, such as
+    /// a gallery comment, is not one.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.trim_start().starts_with(BANNED_STRING) {
+            return Err(REGEX_MATCH_FAILED);
+        }
+
+        let regex = Regex::new(PATTERN_EXPIRES).unwrap();
+        let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+
+        let regex = Regex::new(PATTERN_UNIT).unwrap();
+        let mut seconds = 0;
+        for cap in regex.captures_iter(&captures[1]) {
+            let value = cap[1].parse::<u64>()?;
+            seconds += value * match &cap[2] {
+                "day" => 24 * 60 * 60,
+                "hour" => 60 * 60,
+                "minute" => 60,
+                _ => 1,
+            };
+        }
+
+        Ok(IpBan { expires_in: Duration::from_secs(seconds) })
+    }
+}
+
+const BANNED_STRING: &str = "Your IP address has been temporarily banned";
+const PATTERN_EXPIRES: &str = r#"The ban expires in ([^.<]+)"#;
+const PATTERN_UNIT: &str = r#"(\d+) (day|hour|minute|second)s?"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let s = "Your IP address has been temporarily banned for excessive pageloads which indicates that you are using automated mirroring/harvesting software. The ban expires in 23 minutes and 12 seconds";
+        assert_eq!(s.parse::<IpBan>().unwrap(), IpBan { expires_in: Duration::from_secs(23 * 60 + 12) });
+
+        let s = "Your IP address has been temporarily banned for excessive pageloads. The ban expires in 1 day, 2 hours and 1 second.";
+        assert_eq!(s.parse::<IpBan>().unwrap(), IpBan { expires_in: Duration::from_secs(26 * 60 * 60 + 1) });
+
+        assert!("<html></html>".parse::<IpBan>().is_err());
+
+        let s = "<html><body><div class=\"c6\">Your IP address has been temporarily banned for excessive pageloads. The ban expires in 5 minutes</div></body></html>";
+        assert!(s.parse::<IpBan>().is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::str::FromStr;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use crate::{
+    eh_config,
+    parser::ParseError,
+    structures::{Confidence, Language, LanguageDetection, LanguageVariant},
+};
+
+impl FromStr for Language {
+    type Err = ParseError;
+
+    /// The name shown by the site, case insensitive.
+    ///
+    /// ```text
+    /// Japanese
+    /// N/A
+    /// ```
+    /// Languages the site has no category for are `Language::Other`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        let language = LANGUAGE_NAMES.iter()
+            .find(|(name, _)| *name == s)
+            .map(|(_, language)| *language)
+            .unwrap_or(Language::Other);
+
+        Ok(language)
+    }
+}
+
+impl Language {
+    /// The value of this language in the excluded languages of uconfig (`xl`).
+    pub fn excluded_language_code(&self, variant: LanguageVariant) -> &'static str {
+        let (_, codes) = EXCLUDED_LANGUAGE_CODES.iter()
+            .find(|(language, _)| language == self)
+            .unwrap();
+
+        match variant {
+            LanguageVariant::Original => codes[0],
+            LanguageVariant::Translated => codes[1],
+            LanguageVariant::Rewrite => codes[2],
+        }
+    }
+
+    /// The ISO 639-1 code, `None` for `NotApplicable` and `Other`.
+    pub fn iso_code_opt(&self) -> Option<&'static str> {
+        ISO_CODES.iter()
+            .find(|(language, _)| language == self)
+            .map(|(_, code)| *code)
+    }
+}
+
+impl LanguageDetection {
+    /// Try the tags first, then the markers of the title, then the Language
+    /// field of the detail page. The script of the title is the last resort.
+    pub fn detect(tag_vec: &[String], title: &str, detail_opt: Option<(Language, LanguageVariant)>) -> Option<LanguageDetection> {
+        LanguageDetection::from_tags(tag_vec)
+            .or_else(|| LanguageDetection::from_title(title))
+            .or_else(|| detail_opt.map(|(language, variant)| LanguageDetection { language, variant, confidence: Confidence::High }))
+            .or_else(|| LanguageDetection::from_script(title))
+    }
+
+    /// ```text
+    /// language:english
+    /// language:translated
+    /// ```
+    pub fn from_tags(tag_vec: &[String]) -> Option<LanguageDetection> {
+        let mut language_opt = None;
+        let mut variant = LanguageVariant::Original;
+
+        for tag in tag_vec {
+            let Some(name) = tag.strip_prefix(LANGUAGE_NAMESPACE) else {
+                continue;
+            };
+
+            match name {
+                TAG_TRANSLATED => variant = LanguageVariant::Translated,
+                TAG_REWRITE => variant = LanguageVariant::Rewrite,
+                // `speechless` and `text cleaned` are no language.
+                _ if language_opt.is_none() && LANGUAGE_NAMES.iter().any(|(n, _)| *n == name) => {
+                    language_opt = name.parse::<Language>().ok();
+                }
+                _ => {}
+            }
+        }
+
+        language_opt.map(|language| LanguageDetection { language, variant, confidence: Confidence::High })
+    }
+
+    /// Look for markers such as `[English]` or `中国翻訳`.
+    pub fn from_title(title: &str) -> Option<LanguageDetection> {
+        for (language, regex) in TITLE_REGEXES.iter() {
+            if !regex.is_match(title) {
+                continue;
+            }
+
+            let variant = if REGEX_TRANSLATED.is_match(title) {
+                LanguageVariant::Translated
+            } else {
+                LanguageVariant::Original
+            };
+
+            return Some(LanguageDetection { language: *language, variant, confidence: Confidence::Medium });
+        }
+
+        None
+    }
+
+    /// A title written in kana is most likely Japanese.
+    pub fn from_script(title: &str) -> Option<LanguageDetection> {
+        if REGEX_KANA.is_match(title) {
+            return Some(LanguageDetection {
+                language: Language::Japanese,
+                variant: LanguageVariant::Original,
+                confidence: Confidence::Low,
+            });
+        }
+
+        None
+    }
+}
+
+const LANGUAGE_NAMESPACE: &str = "language:";
+const TAG_TRANSLATED: &str = "translated";
+const TAG_REWRITE: &str = "rewrite";
+
+const LANGUAGE_NAMES: [(&str, Language); 17] = [
+    ("japanese", Language::Japanese),
+    ("english", Language::English),
+    ("chinese", Language::Chinese),
+    ("dutch", Language::Dutch),
+    ("french", Language::French),
+    ("german", Language::German),
+    ("hungarian", Language::Hungarian),
+    ("italian", Language::Italian),
+    ("korean", Language::Korean),
+    ("polish", Language::Polish),
+    ("portuguese", Language::Portuguese),
+    ("russian", Language::Russian),
+    ("spanish", Language::Spanish),
+    ("thai", Language::Thai),
+    ("vietnamese", Language::Vietnamese),
+    ("n/a", Language::NotApplicable),
+    ("other", Language::Other),
+];
+
+const EXCLUDED_LANGUAGE_CODES: [(Language, [&str; 3]); 17] = [
+    (Language::Japanese, [eh_config::JAPANESE_ORIGINAL, eh_config::JAPANESE_TRANSLATED, eh_config::JAPANESE_REWRITE]),
+    (Language::English, [eh_config::ENGLISH_ORIGINAL, eh_config::ENGLISH_TRANSLATED, eh_config::ENGLISH_REWRITE]),
+    (Language::Chinese, [eh_config::CHINESE_ORIGINAL, eh_config::CHINESE_TRANSLATED, eh_config::CHINESE_REWRITE]),
+    (Language::Dutch, [eh_config::DUTCH_ORIGINAL, eh_config::DUTCH_TRANSLATED, eh_config::DUTCH_REWRITE]),
+    (Language::French, [eh_config::FRENCH_ORIGINAL, eh_config::FRENCH_TRANSLATED, eh_config::FRENCH_REWRITE]),
+    (Language::German, [eh_config::GERMAN_ORIGINAL, eh_config::GERMAN_TRANSLATED, eh_config::GERMAN_REWRITE]),
+    (Language::Hungarian, [eh_config::HUNGARIAN_ORIGINAL, eh_config::HUNGARIAN_TRANSLATED, eh_config::HUNGARIAN_REWRITE]),
+    (Language::Italian, [eh_config::ITALIAN_ORIGINAL, eh_config::ITALIAN_TRANSLATED, eh_config::ITALIAN_REWRITE]),
+    (Language::Korean, [eh_config::KOREAN_ORIGINAL, eh_config::KOREAN_TRANSLATED, eh_config::KOREAN_REWRITE]),
+    (Language::Polish, [eh_config::POLISH_ORIGINAL, eh_config::POLISH_TRANSLATED, eh_config::POLISH_REWRITE]),
+    (Language::Portuguese, [eh_config::PORTUGUESE_ORIGINAL, eh_config::PORTUGUESE_TRANSLATED, eh_config::PORTUGUESE_REWRITE]),
+    (Language::Russian, [eh_config::RUSSIAN_ORIGINAL, eh_config::RUSSIAN_TRANSLATED, eh_config::RUSSIAN_REWRITE]),
+    (Language::Spanish, [eh_config::SPANISH_ORIGINAL, eh_config::SPANISH_TRANSLATED, eh_config::SPANISH_REWRITE]),
+    (Language::Thai, [eh_config::THAI_ORIGINAL, eh_config::THAI_TRANSLATED, eh_config::THAI_REWRITE]),
+    (Language::Vietnamese, [eh_config::VIETNAMESE_ORIGINAL, eh_config::VIETNAMESE_TRANSLATED, eh_config::VIETNAMESE_REWRITE]),
+    (Language::NotApplicable, [eh_config::NA_ORIGINAL, eh_config::NA_TRANSLATED, eh_config::NA_REWRITE]),
+    (Language::Other, [eh_config::OTHER_ORIGINAL, eh_config::OTHER_TRANSLATED, eh_config::OTHER_REWRITE]),
+];
+
+const ISO_CODES: [(Language, &str); 15] = [
+    (Language::Japanese, "ja"),
+    (Language::English, "en"),
+    (Language::Chinese, "zh"),
+    (Language::Dutch, "nl"),
+    (Language::French, "fr"),
+    (Language::German, "de"),
+    (Language::Hungarian, "hu"),
+    (Language::Italian, "it"),
+    (Language::Korean, "ko"),
+    (Language::Polish, "pl"),
+    (Language::Portuguese, "pt"),
+    (Language::Russian, "ru"),
+    (Language::Spanish, "es"),
+    (Language::Thai, "th"),
+    (Language::Vietnamese, "vi"),
+];
+
+const TITLE_PATTERNS: [(Language, &str); 15] = [
+    (Language::English, r#"(?i)[(\[]eng(?:lish)?[)\]]|英訳"#),
+    (Language::Chinese, r#"(?i)[(（\[]ch(?:inese)?[)）\]]|[汉漢]化|中[国國][语語]|中文|中国翻訳"#),
+    (Language::Spanish, r#"(?i)[(\[]spanish[)\]]|[(\[]Español[)\]]|スペイン翻訳"#),
+    (Language::Korean, r#"(?i)[(\[]korean?[)\]]|韓国翻訳"#),
+    (Language::Russian, r#"(?i)[(\[]rus(?:sian)?[)\]]|ロシア翻訳"#),
+    (Language::French, r#"(?i)[(\[]fr(?:ench)?[)\]]|フランス翻訳"#),
+    (Language::Portuguese, r#"(?i)[(\[]portuguese|ポルトガル翻訳"#),
+    (Language::Thai, r#"(?i)[(\[]thai(?: ภาษาไทย)?[)\]]|แปลไทย|タイ翻訳"#),
+    (Language::German, r#"(?i)[(\[]german[)\]]|ドイツ翻訳"#),
+    (Language::Italian, r#"(?i)[(\[]italiano?[)\]]|イタリア翻訳"#),
+    (Language::Vietnamese, r#"(?i)[(\[]vietnamese(?: Tiếng Việt)?[)\]]|ベトナム翻訳"#),
+    (Language::Polish, r#"(?i)[(\[]polish[)\]]|ポーランド翻訳"#),
+    (Language::Hungarian, r#"(?i)[(\[]hun(?:garian)?[)\]]|ハンガリー翻訳"#),
+    (Language::Dutch, r#"(?i)[(\[]dutch[)\]]|オランダ翻訳"#),
+    (Language::Japanese, r#"(?i)[(\[]japanese[)\]]"#),
+];
+
+const PATTERN_TRANSLATED: &str = r#"(?i)翻訳|[英汉漢]訳|[汉漢]化|translat(?:ed|ion)"#;
+const PATTERN_KANA: &str = r#"[\p{Hiragana}\p{Katakana}]"#;
+
+// Compiled once, detection runs for every gallery of a list.
+static TITLE_REGEXES: Lazy<Vec<(Language, Regex)>> = Lazy::new(|| {
+    TITLE_PATTERNS.iter()
+        .map(|(language, pattern)| (*language, Regex::new(pattern).unwrap()))
+        .collect()
+});
+static REGEX_TRANSLATED: Lazy<Regex> = Lazy::new(|| Regex::new(PATTERN_TRANSLATED).unwrap());
+static REGEX_KANA: Lazy<Regex> = Lazy::new(|| Regex::new(PATTERN_KANA).unwrap());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!("Japanese".parse::<Language>().unwrap(), Language::Japanese);
+        assert_eq!(" N/A ".parse::<Language>().unwrap(), Language::NotApplicable);
+        assert_eq!("Ukrainian".parse::<Language>().unwrap(), Language::Other);
+    }
+
+    #[test]
+    fn detect_test() {
+        let tag_vec = vec![String::from("language:translated"), String::from("language:english")];
+        assert_eq!(LanguageDetection::detect(&tag_vec, "", None), Some(LanguageDetection {
+            language: Language::English,
+            variant: LanguageVariant::Translated,
+            confidence: Confidence::High,
+        }));
+
+        let title = "(C99) [Circle (Artist)] Title (Original) [Chinese] [某汉化组]";
+        assert_eq!(LanguageDetection::detect(&[], title, None), Some(LanguageDetection {
+            language: Language::Chinese,
+            variant: LanguageVariant::Translated,
+            confidence: Confidence::Medium,
+        }));
+
+        let detail_opt = Some((Language::Korean, LanguageVariant::Rewrite));
+        assert_eq!(LanguageDetection::detect(&[], "[サークル] タイトル", detail_opt), Some(LanguageDetection {
+            language: Language::Korean,
+            variant: LanguageVariant::Rewrite,
+            confidence: Confidence::High,
+        }));
+
+        let title = "[サークル] タイトル";
+        assert_eq!(LanguageDetection::detect(&[], title, None).unwrap().confidence, Confidence::Low);
+
+        assert_eq!(LanguageDetection::detect(&[], "[Circle] Title", None), None);
+    }
+
+    #[test]
+    fn excluded_language_code_test() {
+        assert_eq!(Language::Japanese.excluded_language_code(LanguageVariant::Original), "0");
+        assert_eq!(Language::English.excluded_language_code(LanguageVariant::Translated), "1025");
+        assert_eq!(Language::Other.excluded_language_code(LanguageVariant::Rewrite), "2303");
+    }
+}
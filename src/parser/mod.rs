@@ -1,3 +1,6 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
 mod input;
 mod unescape;
 mod archive_parser;
@@ -10,7 +13,7 @@ mod gallery_list_parser;
 mod gallery_info_parser;
 mod gallery_api_parser;
 mod gallery_identity_parser;
-mod gallery_multi_page_viewer_p_token_parser;
+mod gallery_multi_page_viewer_parser;
 mod gallery_not_available_parser;
 mod gallery_page_api_parser;
 mod gallery_page_parser;
@@ -28,6 +31,10 @@ mod torrent_parser;
 mod vote_comment_parser;
 mod vote_tag_parser;
 mod category_parser;
+mod ip_ban_parser;
+mod language_parser;
+mod parsed_title_parser;
+mod image_limits_parser;
 
 // result
 
@@ -49,6 +56,14 @@ pub enum ParseError {
     AttributeNotFound,
     DomNotFound,
     FromServer(String),
+    /// The offensive content warning was served instead of the gallery.
+    ContentWarning,
+    /// The gallery has been expunged, "this gallery is pining for the fjords".
+    GalleryPining,
+    /// The server explained why the gallery cannot be viewed.
+    GalleryNotAvailable(crate::structures::GalleryUnavailable),
+    /// The image quota of the account is used up, the 509 image was served instead.
+    ImageQuotaExceeded,
     Other(BoxDynError),
 }
 
@@ -61,6 +76,10 @@ impl std::fmt::Display for ParseError {
             ParseError::AttributeNotFound => write!(f, "attribute cannot be found"),
             ParseError::DomNotFound => write!(f, "dom cannot be found"),
             ParseError::FromServer(s) => write!(f, "error from server: {}", s),
+            ParseError::ContentWarning => write!(f, "this gallery is behind a content warning"),
+            ParseError::GalleryPining => write!(f, "this gallery is pining for the fjords"),
+            ParseError::GalleryNotAvailable(kind) => kind.fmt(f),
+            ParseError::ImageQuotaExceeded => write!(f, "the image quota is exceeded"),
             ParseError::Other(e) => e.fmt(f),
         }
     }
@@ -73,6 +92,43 @@ const OUT_OF_RANGE: ParseError = ParseError::OutOfRange;
 const SIGN_IN_REQUIRED: ParseError = ParseError::SignInRequired;
 const ATTRIBUTE_NOT_FOUND: ParseError = ParseError::AttributeNotFound;
 const DOM_NOT_FOUND: ParseError = ParseError::DomNotFound;
+const CONTENT_WARNING: ParseError = ParseError::ContentWarning;
+const GALLERY_PINING: ParseError = ParseError::GalleryPining;
+const IMAGE_QUOTA_EXCEEDED: ParseError = ParseError::ImageQuotaExceeded;
+
+const PATTERN_FILE_SIZE: &str = r"([\d.]+)\s*([KMGT]?i?B)";
+const QUOTA_EXCEEDED_IMAGES: [&str; 2] = ["/509.gif", "/509s.gif"];
+
+/// Whether `url` is the image served in place of the real one once the
+/// image quota is used up.
+pub(crate) fn is_quota_exceeded_image(url: &str) -> bool {
+    QUOTA_EXCEEDED_IMAGES.iter().any(|image| url.ends_with(image))
+}
+
+/// `2021-11-28 10:12`, in UTC.
+fn parse_posted(s: &str) -> EhParseResult<DateTime<Utc>> {
+    let fmt = "%Y-%m-%d %H:%M %z";
+    let date_str = format!("{} +0000", s.trim());
+    let datetime = DateTime::parse_from_str(&date_str, fmt)?;
+    Ok(datetime.with_timezone(&Utc))
+}
+
+/// `345.2 MiB`, the site counts in powers of 1024 whatever the unit says.
+fn parse_file_size(s: &str) -> EhParseResult<u64> {
+    let regex = Regex::new(PATTERN_FILE_SIZE).unwrap();
+    let captures = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+    let size = captures[1].parse::<f64>()?;
+
+    let exponent = match captures[2].to_uppercase().chars().next() {
+        Some('K') => 1,
+        Some('M') => 2,
+        Some('G') => 3,
+        Some('T') => 4,
+        _ => 0,
+    };
+
+    Ok((size * 1024_f64.powi(exponent)).round() as u64)
+}
 
 impl From<BoxDynError> for ParseError {
     fn from(value: BoxDynError) -> Self {
@@ -0,0 +1,198 @@
+use std::str::FromStr;
+use regex::Regex;
+use crate::{parser::ParseError, structures::{LanguageDetection, ParsedTitle}};
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum GroupKind {
+    Paren,
+    Bracket,
+    Brace,
+}
+
+impl FromStr for ParsedTitle {
+    type Err = ParseError;
+
+    /// Works with `title` as well as `title_jpn`, full-width brackets included.
+    ///
+    /// ```text
+    /// (C99) [Circle (Artist)] Title (Parody) [English] [Translator] [Digital] ch.1-23
+    /// (C99) [サークル (作家)] タイトル (パロディ) [中国翻訳] [DL版]
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parsed = ParsedTitle::default();
+        let mut rest = s.trim();
+
+        // 1. prefix: (Event) [Circle (Artist)]
+        let regex = Regex::new(&format!(r"^\s*(?:{})", PATTERN_GROUP)).unwrap();
+        let regex_circle = Regex::new(PATTERN_CIRCLE).unwrap();
+        while let Some(captures) = regex.captures(rest) {
+            let m = captures.get(0).unwrap();
+            if rest[m.end()..].trim().is_empty() {
+                break;
+            }
+
+            let (kind, text) = group_of(&captures);
+            match kind {
+                GroupKind::Paren if parsed.event_opt.is_none() && parsed.artist_opt.is_none() && parsed.circle_opt.is_none() => {
+                    parsed.event_opt = Some(text);
+                }
+                GroupKind::Bracket if parsed.artist_opt.is_none() && parsed.circle_opt.is_none() => {
+                    if let Some(captures) = regex_circle.captures(&text) {
+                        parsed.circle_opt = Some(String::from(captures[1].trim()));
+                        parsed.artist_opt = Some(String::from(captures[2].trim()));
+                    } else {
+                        parsed.artist_opt = Some(text);
+                    }
+                }
+                _ => parsed.extra_vec.push(text),
+            }
+
+            rest = &rest[m.end()..];
+        }
+
+        // 2. suffix: (Parody) [Language] [Translator] [Digital] [Decensored] ch.1-23
+        let regex_group = Regex::new(&format!(r"(?:{})\s*$", PATTERN_GROUP)).unwrap();
+        let regex_chapter = Regex::new(PATTERN_CHAPTER).unwrap();
+        let mut suffix_vec = vec![];
+        loop {
+            if let Some(captures) = regex_chapter.captures(rest) {
+                let m = captures.get(0).unwrap();
+                if !rest[..m.start()].trim().is_empty() {
+                    parsed.chapter_opt.get_or_insert_with(|| String::from(captures[1].trim()));
+                    rest = &rest[..m.start()];
+                    continue;
+                }
+            }
+
+            let Some(captures) = regex_group.captures(rest) else {
+                break;
+            };
+
+            let m = captures.get(0).unwrap();
+            if rest[..m.start()].trim().is_empty() {
+                break;
+            }
+
+            suffix_vec.push(group_of(&captures));
+            rest = &rest[..m.start()];
+        }
+
+        for (kind, text) in suffix_vec.into_iter().rev() {
+            let lower = text.to_lowercase();
+            if kind == GroupKind::Paren {
+                if parsed.parody_opt.is_none() {
+                    parsed.parody_opt = Some(text);
+                } else {
+                    parsed.extra_vec.push(text);
+                }
+            } else if DIGITAL_STRINGS.contains(&lower.as_str()) {
+                parsed.is_digital = true;
+            } else if DECENSORED_STRINGS.contains(&lower.as_str()) {
+                parsed.is_decensored = true;
+            } else if parsed.language_opt.is_none() && kind == GroupKind::Bracket && is_language(&text) {
+                parsed.language_opt = LanguageDetection::from_title(&format!("[{}]", text)).map(|d| d.language);
+            } else if parsed.translator_opt.is_none() && (parsed.language_opt.is_some() || kind == GroupKind::Brace) {
+                parsed.translator_opt = Some(text);
+            } else {
+                parsed.extra_vec.push(text);
+            }
+        }
+
+        parsed.title = String::from(rest.trim());
+        if parsed.title.is_empty() {
+            parsed.title = String::from(s.trim());
+        }
+
+        Ok(parsed)
+    }
+}
+
+impl ParsedTitle {
+    /// The core title in lowercase, with punctuation and repeated spaces
+    /// removed, to compare titles of different uploads.
+    pub fn normalized_title(&self) -> String {
+        self.title
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c.to_lowercase().next().unwrap() } else { ' ' })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+fn group_of(captures: &regex::Captures) -> (GroupKind, String) {
+    let (kind, idx) = [
+        (GroupKind::Paren, 1),
+        (GroupKind::Paren, 2),
+        (GroupKind::Bracket, 3),
+        (GroupKind::Bracket, 4),
+        (GroupKind::Brace, 5),
+    ].into_iter().find(|(_, idx)| captures.get(*idx).is_some()).unwrap();
+
+    (kind, String::from(captures[idx].trim()))
+}
+
+fn is_language(text: &str) -> bool {
+    LanguageDetection::from_title(&format!("[{}]", text)).is_some()
+}
+
+// 1. 2. (XXX) or （XXX）
+// 3. 4. [XXX] or 【XXX】
+// 5. {XXX}
+const PATTERN_GROUP: &str = r"\(([^()（）]*)\)|（([^()（）]*)）|\[([^\[\]【】]*)\]|【([^\[\]【】]*)】|\{([^{}]*)\}";
+const PATTERN_CIRCLE: &str = r"^(.+?)\s*[(（]([^()（）]+)[)）]$";
+const PATTERN_CHAPTER: &str = r"(?i)\s+((?:ch|chapter|vol)\.?\s*\d+(?:\s*[-~]\s*\d+)?)\s*$";
+
+// Compared against the lowercase group.
+const DIGITAL_STRINGS: [&str; 3] = ["digital", "dl版", "dl"];
+const DECENSORED_STRINGS: [&str; 3] = ["decensored", "uncensored", "無修正"];
+
+#[cfg(test)]
+mod tests {
+    use crate::structures::Language;
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let s = "(C99) [Circle (Artist)] Some Title (Original Work) [English] [Translator] [Digital] [Decensored] ch.1-23";
+        assert_eq!(s.parse::<ParsedTitle>().unwrap(), ParsedTitle {
+            event_opt: Some(String::from("C99")),
+            circle_opt: Some(String::from("Circle")),
+            artist_opt: Some(String::from("Artist")),
+            title: String::from("Some Title"),
+            parody_opt: Some(String::from("Original Work")),
+            language_opt: Some(Language::English),
+            translator_opt: Some(String::from("Translator")),
+            is_digital: true,
+            is_decensored: true,
+            chapter_opt: Some(String::from("ch.1-23")),
+            extra_vec: vec![],
+        });
+
+        let s = "(C99) [サークル (作家)] タイトル （パロディ） [中国翻訳] [DL版]";
+        let parsed = s.parse::<ParsedTitle>().unwrap();
+        assert_eq!(parsed.circle_opt.as_deref(), Some("サークル"));
+        assert_eq!(parsed.title, "タイトル");
+        assert_eq!(parsed.parody_opt.as_deref(), Some("パロディ"));
+        assert_eq!(parsed.language_opt, Some(Language::Chinese));
+        assert!(parsed.is_digital);
+
+        let s = "[Artist] Title Only";
+        let parsed = s.parse::<ParsedTitle>().unwrap();
+        assert_eq!(parsed.circle_opt, None);
+        assert_eq!(parsed.artist_opt.as_deref(), Some("Artist"));
+        assert_eq!(parsed.title, "Title Only");
+
+        let s = "[Only Brackets]";
+        assert_eq!(s.parse::<ParsedTitle>().unwrap().title, "[Only Brackets]");
+    }
+
+    #[test]
+    fn normalized_title_test() {
+        let a = "(C99) [Circle (Artist)] Some  Title! (Original Work) [English]".parse::<ParsedTitle>().unwrap();
+        let b = "[Circle (Artist)] some title (Original Work) [Chinese] [Digital]".parse::<ParsedTitle>().unwrap();
+        assert_eq!(a.normalized_title(), "some title");
+        assert_eq!(a.normalized_title(), b.normalized_title());
+    }
+}
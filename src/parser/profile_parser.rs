@@ -1,11 +1,26 @@
 use std::str::FromStr;
+use chrono::NaiveDate;
+use regex::Regex;
 use visdom::Vis;
 use crate::eh_url;
-use crate::{parser::{ATTRIBUTE_NOT_FOUND, ParseError}, structures::Profile};
+use crate::{parser::{ATTRIBUTE_NOT_FOUND, ParseError, unescape::unescape}, structures::Profile};
 
 impl FromStr for Profile {
     type Err = ParseError;
 
+    /// ```html
+    /// <div id="profilename"><font ...>xxxx</font></div>
+    /// <br />
+    /// <div><img src="https://forums.e-hentai.org/uploads/av-1234567.jpg" ... /></div>
+    /// ...
+    /// <div class="postdetails">
+    ///     Group: Members<br />
+    ///     Joined: 12-March 10<br />
+    ///     Posts: 1,234<br />
+    /// </div>
+    /// ...
+    /// <div class="signature">...</div>
+    /// ```
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let root = Vis::load(s)?;
         let display_name = root.find("#profilename > font");
@@ -18,13 +33,45 @@ impl FromStr for Profile {
             avatar = format!("{}{}", eh_url::URL_FORUMS, avatar);
         }
 
+        let regex = Regex::new(PATTERN_MEMBER_ID).unwrap();
+        let member_id_opt = regex.captures(s).and_then(|cap| cap[1].parse::<u64>().ok());
+
+        let regex = Regex::new(PATTERN_GROUP).unwrap();
+        let group_opt = regex.captures(s).map(|cap| String::from(unescape(cap[1].trim())));
+
+        let regex = Regex::new(PATTERN_JOINED).unwrap();
+        let join_date_opt = regex.captures(s)
+            .and_then(|cap| NaiveDate::parse_from_str(cap[1].trim(), JOINED_FORMAT).ok());
+
+        let regex = Regex::new(PATTERN_POSTS).unwrap();
+        let post_count_opt = regex.captures(s)
+            .and_then(|cap| cap[1].replace(',', "").parse::<u32>().ok());
+
+        let signature = root.find(".signature");
+        let signature_opt = if signature.is_empty() {
+            None
+        } else {
+            Some(signature.html().trim().to_string())
+        };
+
         Ok(Profile {
+            member_id_opt,
             display_name,
             avatar,
+            group_opt,
+            join_date_opt,
+            post_count_opt,
+            signature_opt,
         })
     }
 }
 
+const PATTERN_MEMBER_ID: &str = r#"[?&;]MID=(\d+)"#;
+const PATTERN_GROUP: &str = r#"Group:\s*(?:<[^>]+>\s*)*([^<\n]+)"#;
+const PATTERN_JOINED: &str = r#"Joined:\s*(?:<[^>]+>\s*)*([^<\n]+)"#;
+const PATTERN_POSTS: &str = r#"Posts:\s*(?:<[^>]+>\s*)*([\d,]+)"#;
+const JOINED_FORMAT: &str = "%d-%B %y";
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -34,10 +81,41 @@ mod tests {
     #[test]
     fn forums_parse_test() {
         let s = read_test_file("profile.html");
+        let profile = s.parse::<Profile>().unwrap();
+
+        assert_eq!(profile.display_name, String::from(r#"xxxx"#));
+        assert_eq!(profile.avatar, format!("{}{}", eh_url::URL_FORUMS, "style_images/ambience/warn0.gif"));
+    }
+
+    #[test]
+    fn parse_details_test() {
+        let ele = r##"
+            <table class="ipbtable" cellspacing="1">
+                <tr>
+                    <td class="row1">
+                        <div id="profilename"><font color="#000">xxxx</font></div>
+                        <br />
+                        <div><img src="uploads/av-1234567.jpg" alt="" /></div>
+                        <div class="postdetails">
+                            Group: Members<br />
+                            Joined: 12-March 10<br />
+                            Posts: 1,234<br />
+                        </div>
+                        <a href="https://forums.e-hentai.org/index.php?act=Msg&amp;CODE=4&amp;MID=1234567">Send Message</a>
+                        <div class="signature">hello<br />world</div>
+                    </td>
+                </tr>
+            </table>
+        "##;
 
-        assert_eq!(s.parse::<Profile>().unwrap(), Profile {
-            display_name: String::from(r#"xxxx"#),
-            avatar: format!("{}{}", eh_url::URL_FORUMS, "style_images/ambience/warn0.gif"),
+        assert_eq!(ele.parse::<Profile>().unwrap(), Profile {
+            member_id_opt: Some(1234567),
+            display_name: String::from("xxxx"),
+            avatar: format!("{}{}", eh_url::URL_FORUMS, "uploads/av-1234567.jpg"),
+            group_opt: Some(String::from("Members")),
+            join_date_opt: NaiveDate::from_ymd_opt(2010, 3, 12),
+            post_count_opt: Some(1234),
+            signature_opt: Some(String::from("hello<br />world")),
         });
     }
 }
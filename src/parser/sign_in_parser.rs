@@ -1,6 +1,6 @@
 use std::str::FromStr;
 use regex::Regex;
-use crate::{parser::{ParseError, REGEX_MATCH_FAILED}, structures::SignIn};
+use crate::{parser::{ParseError, REGEX_MATCH_FAILED}, structures::{SignIn, SignInError}};
 
 impl FromStr for SignIn {
     type Err = ParseError;
@@ -10,7 +10,7 @@ impl FromStr for SignIn {
         if let Some(cap) = regex.captures(s) {
             let username = String::from(&cap[1]);
 
-            Ok(SignIn { username })
+            Ok(SignIn { username, member_id_opt: None })
         } else {
             let regex = Regex::new(ERROR_PATTERN).unwrap();
             if let Some(cap) = regex.captures(s) {
@@ -30,9 +30,65 @@ impl FromStr for SignIn {
     }
 }
 
+impl FromStr for SignInError {
+    type Err = ParseError;
+
+    /// Classify a failed login page, fails if `s` is not one.
+    ///
+    /// ```html
+    /// <h4>The error returned was:</h4>
+    /// <p>Sorry, the password was wrong. All passwords are case sensitive.</p>
+    /// ```
+    /// Or the login form again, with a reCAPTCHA block in it.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if Regex::new(NAME_PATTERN).unwrap().is_match(s) {
+            return Err(REGEX_MATCH_FAILED);
+        }
+
+        if s.contains(RECAPTCHA_STRING) {
+            return Ok(SignInError::CaptchaRequired);
+        }
+
+        let lower = s.to_lowercase();
+        if FORUM_DOWN_STRINGS.iter().any(|down| lower.contains(down)) {
+            return Ok(SignInError::ForumDown);
+        }
+
+        let regex = Regex::new(ERROR_PATTERN).unwrap();
+        let cap = regex.captures(s).ok_or(REGEX_MATCH_FAILED)?;
+        let error = cap.get(1).or_else(|| cap.get(2)).ok_or(REGEX_MATCH_FAILED)?.as_str();
+
+        let lower = error.to_lowercase();
+        let contains_any = |strings: &[&str]| strings.iter().any(|x| lower.contains(x));
+
+        let error = if contains_any(&TOO_MANY_ATTEMPTS_STRINGS) {
+            SignInError::TooManyAttempts
+        } else if contains_any(&CAPTCHA_STRINGS) {
+            SignInError::CaptchaRequired
+        } else if contains_any(&WRONG_PASSWORD_STRINGS) {
+            SignInError::WrongPassword
+        } else if contains_any(&ACCOUNT_NOT_FOUND_STRINGS) {
+            SignInError::AccountNotFound
+        } else {
+            SignInError::Other(String::from(error))
+        };
+
+        Ok(error)
+    }
+}
+
 const NAME_PATTERN: &str = "<p>You are now logged in as: (.+?)<";
 const ERROR_PATTERN: &str = r#"(?:<h4>The error returned was:</h4>\s*<p>(.+?)</p>)|(?:<span class="postcolor">(.+?)</span>)"#;
 
+const RECAPTCHA_STRING: &str = "g-recaptcha";
+
+// Compared against the lowercase page.
+const FORUM_DOWN_STRINGS: [&str; 3] = ["board is currently offline", "mysql query error", "database error"];
+const TOO_MANY_ATTEMPTS_STRINGS: [&str; 3] = ["too many", "maximum number of", "try again later"];
+const CAPTCHA_STRINGS: [&str; 1] = ["captcha"];
+const WRONG_PASSWORD_STRINGS: [&str; 2] = ["password was wrong", "password incorrect"];
+const ACCOUNT_NOT_FOUND_STRINGS: [&str; 2] = ["could not find a member", "no such member"];
+
 #[cfg(test)]
 mod tests {
     use crate::test_helper::read_test_file;
@@ -46,4 +102,38 @@ mod tests {
         let s = read_test_file("sign_in_success.html");
         assert_eq!(s.parse::<SignIn>().is_ok(), true);
     }
+
+    #[test]
+    fn parse_error_test() {
+        let ele = r#"
+            <div class="errorwrap">
+                <h4>The error returned was:</h4>
+                <p>Sorry, the password was wrong. All passwords are case sensitive.</p>
+            </div>
+        "#;
+        assert_eq!(ele.parse::<SignInError>().unwrap(), SignInError::WrongPassword);
+
+        let ele = r#"<h4>The error returned was:</h4><p>We could not find a member using those log in details.</p>"#;
+        assert_eq!(ele.parse::<SignInError>().unwrap(), SignInError::AccountNotFound);
+
+        let ele = r#"<span class="postcolor">You have made too many login attempts, try again later.</span>"#;
+        assert_eq!(ele.parse::<SignInError>().unwrap(), SignInError::TooManyAttempts);
+
+        let ele = r#"<form><div class="g-recaptcha" data-sitekey="xxxx"></div></form>"#;
+        assert_eq!(ele.parse::<SignInError>().unwrap(), SignInError::CaptchaRequired);
+
+        let ele = r#"<p>You are now logged in as: xxxx<br></p>"#;
+        assert!(ele.parse::<SignInError>().is_err());
+    }
+
+    #[test]
+    fn parse_success_with_postcolor_test() {
+        let ele = r#"
+            <div class="g-recaptcha"></div>
+            <p>You are now logged in as: xxxx<br></p>
+            <div class="postcolor"><span class="postcolor">Welcome back</span></div>
+        "#;
+        assert_eq!(ele.parse::<SignIn>().unwrap().username, "xxxx");
+        assert!(ele.parse::<SignInError>().is_err());
+    }
 }
@@ -1,12 +1,16 @@
+use crate::eh_config;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Settings {
     site: GallerySites,
+    content_warning: ContentWarning,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Settings {
             site: GallerySites::E,
+            content_warning: ContentWarning::default(),
         }
     }
 }
@@ -19,6 +23,18 @@ impl Settings {
     pub fn site(&self) -> GallerySites {
         self.site
     }
+
+    pub fn set_site(&mut self, site: GallerySites) {
+        self.site = site;
+    }
+
+    pub fn content_warning(&self) -> ContentWarning {
+        self.content_warning
+    }
+
+    pub fn set_content_warning(&mut self, content_warning: ContentWarning) {
+        self.content_warning = content_warning;
+    }
 }
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -26,3 +42,35 @@ pub enum GallerySites {
     E,
     EX,
 }
+
+/// What to do when a gallery is behind the offensive content warning.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ContentWarning {
+    /// Stop at the warning, fail with `EhError::ContentWarning`.
+    Show,
+    /// Reload the gallery with `nw=1`.
+    Skip,
+    /// Reload the gallery with `nw=always`, the server stops warning this account.
+    SkipAlways,
+}
+
+impl Default for ContentWarning {
+    fn default() -> Self {
+        match *eh_config::contentWarning.lock().unwrap() {
+            eh_config::CONTENT_WARNING_SHOW => ContentWarning::Show,
+            eh_config::CONTENT_WARNING_NOT_SHOW_ALWAYS => ContentWarning::SkipAlways,
+            _ => ContentWarning::Skip,
+        }
+    }
+}
+
+impl ContentWarning {
+    /// The value of the `nw` query parameter, if any.
+    pub fn value_opt(&self) -> Option<&'static str> {
+        match self {
+            ContentWarning::Show => None,
+            ContentWarning::Skip => Some(eh_config::CONTENT_WARNING_NOT_SHOW),
+            ContentWarning::SkipAlways => Some(eh_config::CONTENT_WARNING_NOT_SHOW_ALWAYS),
+        }
+    }
+}
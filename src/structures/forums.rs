@@ -2,4 +2,6 @@
 pub struct Forums {
     /// Links to user profile page.
     pub user_link: String,
+    /// The `showuser` parameter of `user_link`.
+    pub member_id: u64,
 }
@@ -1 +1,40 @@
- 
+use serde::{Deserialize, Serialize};
+
+/// An entry of the gdata API, either the metadata or an error.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GalleryMetadata {
+    pub gid: u64,
+    #[serde(default)]
+    pub token: String,
+    #[serde(alias = r#"error"#)]
+    pub error_opt: Option<String>,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub title_jpn: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub thumb: String,
+    #[serde(default)]
+    pub uploader: String,
+    /// Unix timestamp in seconds.
+    #[serde(default)]
+    pub posted: String,
+    #[serde(alias = r#"filecount"#, default)]
+    pub file_count: String,
+    #[serde(alias = r#"filesize"#, default)]
+    pub file_size: u64,
+    #[serde(default)]
+    pub expunged: bool,
+    #[serde(default)]
+    pub rating: String,
+    #[serde(alias = r#"tags"#, default)]
+    pub tag_vec: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct GalleryMetadataList {
+    #[serde(alias = r#"gmetadata"#)]
+    pub metadata_vec: Vec<GalleryMetadata>,
+}
@@ -1,5 +1,5 @@
-use std::collections::HashMap;
-use crate::structures::{GalleryIdentity, GalleryTagGroup};
+use chrono::{DateTime, Utc};
+use crate::structures::{GalleryIdentity, GalleryTagGroup, Language};
 
 #[derive(Debug, PartialEq)]
 pub struct GalleryDetail {
@@ -11,7 +11,8 @@ pub struct GalleryDetail {
     pub torrent_url: String,
     pub archive_url: String,
     pub thumb: String,
-    pub newer_version_map_opt: Option<HashMap<String, GalleryIdentity>>,
+    /// Oldest first, empty if this is the newest version.
+    pub newer_version_vec: Vec<GalleryVersion>,
     pub is_favorited: bool,
     pub favorite_name_opt: Option<String>,
     pub favorite_slot_opt: Option<u32>,
@@ -29,6 +30,13 @@ pub struct GalleryDetail {
     pub detail: GalleryDetailDetail,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct GalleryVersion {
+    pub identity: GalleryIdentity,
+    pub title: String,
+    pub posted: DateTime<Utc>,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct GalleryComment {
     /// 0 for uploader comment. cannot vote.
@@ -57,15 +65,32 @@ pub struct GalleryCommentList {
 
 #[derive(Debug, PartialEq)]
 pub struct GalleryDetailDetail {
-    pub posted: String,
-    pub parent_opt: Option<String>,
-    pub visible: String,
-    pub language: String,
-    pub file_size: String,
+    pub posted: DateTime<Utc>,
+    pub parent_opt: Option<GalleryIdentity>,
+    pub visible: Visible,
+    pub language: Language,
+    pub is_translated: bool,
+    pub is_rewrite: bool,
+    /// In bytes.
+    pub file_size: u64,
     pub pages: u32,
     pub favorite_count: u32,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub enum Visible {
+    Yes,
+    No(HiddenReason),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum HiddenReason {
+    Replaced,
+    Expunged,
+    Private,
+    Other(String),
+}
+
 #[derive(Debug, PartialEq)]
 pub struct GalleryPreviewLarge {
     pub position: u32,
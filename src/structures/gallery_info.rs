@@ -1,12 +1,13 @@
-use crate::structures::{GalleryIdentity, Thumb};
+use chrono::{DateTime, Utc};
+use crate::structures::{GalleryIdentity, LanguageDetection, Thumb};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Default)]
 pub struct GalleryInfo {
     pub identity: GalleryIdentity,
     pub title: String,
     pub thumb: Thumb,
     pub category: u32,
-    pub posted: String,
+    pub posted: DateTime<Utc>,
     /// 1. gallery list page:
     ///     Some: Minimal MinimalPlus Compact Extended
     ///     None: Thumbnail
@@ -20,7 +21,8 @@ pub struct GalleryInfo {
     pub simple_tag_vec_opt: Option<Vec<String>>,
     pub rating: f32,
     pub pages: u32,
-    pub simple_language_opt: Option<String>,
+    /// From the simple tags of Compact Extended, or the title.
+    pub language_opt: Option<LanguageDetection>,
     pub is_favorited: bool,
     pub favorite_slot_opt: Option<u32>,
     pub favorite_name_opt: Option<String>,
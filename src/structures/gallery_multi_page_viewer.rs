@@ -0,0 +1,49 @@
+use crate::structures::{GalleryPageUrl, ImageSize};
+
+/// The Multi-Page Viewer of a gallery, which lists the key of every page at
+/// once. Only served to accounts with the Multi-Page Viewer perk.
+#[derive(Debug, PartialEq)]
+pub struct GalleryMultiPageViewer {
+    pub gid: u64,
+    /// Sent back with every `imagedispatch` call.
+    pub mpv_key: String,
+    pub pages: u32,
+    pub image_vec: Vec<MultiPageViewerImage>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MultiPageViewerImage {
+    pub filename: String,
+    /// The page token, as in `GalleryPageUrl::p_token`.
+    pub p_token: String,
+    /// `(https://ehgt.org/.../xxx.jpg) -200px 0`, the thumbnail as a CSS background.
+    pub thumb: String,
+}
+
+/// The image of one page, answered by the `imagedispatch` API.
+#[derive(Debug, PartialEq)]
+pub struct MultiPageViewerDispatch {
+    pub image_url: String,
+    /// Sent back as `nl` to get the image from another H@H node.
+    pub skip_hath_key: String,
+    /// `None` if the image shown is already the original.
+    pub origin_image_url_opt: Option<String>,
+    pub image_size: ImageSize,
+    /// `None` if the image shown is already the original.
+    pub origin_image_size_opt: Option<ImageSize>,
+}
+
+impl GalleryMultiPageViewer {
+    /// The page urls in order, the same as `EhClient::all_page_urls` without
+    /// loading every preview page.
+    pub fn page_url_vec(&self) -> Vec<GalleryPageUrl> {
+        self.image_vec.iter()
+            .enumerate()
+            .map(|(page, image)| GalleryPageUrl {
+                gid: self.gid,
+                p_token: image.p_token.clone(),
+                page: page as u32,
+            })
+            .collect()
+    }
+}
@@ -1,4 +0,0 @@
-#[derive(Debug, PartialEq)]
-pub struct GalleryMultiPageViewerPToken {
-    pub image_vec: Vec<String>,
-}
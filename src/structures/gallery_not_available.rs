@@ -1,4 +1,33 @@
 #[derive(Debug, PartialEq)]
 pub struct GalleryNotAvailable {
     pub error: String,
+    pub kind: GalleryUnavailable,
+}
+
+/// Why a gallery cannot be viewed, from its page or from the gdata API.
+#[derive(Debug, PartialEq, Clone)]
+pub enum GalleryUnavailable {
+    Removed,
+    CopyrightClaim { claimant: String },
+    Expunged { reason_opt: Option<String> },
+    InvalidKey,
+    NotFound,
+    /// Try again on exhentai.org.
+    ExhentaiOnly,
+    Other(String),
+}
+
+impl std::fmt::Display for GalleryUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GalleryUnavailable::Removed => write!(f, "this gallery has been removed"),
+            GalleryUnavailable::CopyrightClaim { claimant } => write!(f, "this gallery is unavailable due to a copyright claim by {}", claimant),
+            GalleryUnavailable::Expunged { reason_opt: Some(reason) } => write!(f, "this gallery has been expunged: {}", reason),
+            GalleryUnavailable::Expunged { reason_opt: None } => write!(f, "this gallery has been expunged"),
+            GalleryUnavailable::InvalidKey => write!(f, "the gallery key is missing or incorrect"),
+            GalleryUnavailable::NotFound => write!(f, "gallery not found"),
+            GalleryUnavailable::ExhentaiOnly => write!(f, "this gallery can only be viewed on exhentai"),
+            GalleryUnavailable::Other(s) => write!(f, "{}", s),
+        }
+    }
 }
@@ -1,7 +1,38 @@
+use crate::structures::GalleryPageUrl;
+
 #[derive(Debug, PartialEq)]
 pub struct GalleryPage {
     pub image_url: String,
     pub skip_hath_key: String,
-    pub origin_image_url: String,
+    /// `None` if the image shown is already the original.
+    pub origin_image_url_opt: Option<String>,
     pub show_key: String,
+    pub filename: String,
+    pub image_size: ImageSize,
+    /// `None` if the image shown is already the original.
+    pub origin_image_size_opt: Option<ImageSize>,
+    /// `None` if the page has no navigation links in the usual shape.
+    pub navigation_opt: Option<PageNavigation>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+    /// In bytes.
+    pub file_size: u64,
+}
+
+/// The links above and below the image.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageNavigation {
+    /// From 0.
+    pub page: u32,
+    pub pages: u32,
+    pub first: GalleryPageUrl,
+    /// The current page on the first page.
+    pub prev: GalleryPageUrl,
+    /// The current page on the last page.
+    pub next: GalleryPageUrl,
+    pub last: GalleryPageUrl,
 }
@@ -1,6 +1,15 @@
+use crate::structures::{ImageSize, PageNavigation};
+
 #[derive(Debug, PartialEq)]
 pub struct GalleryPageApi {
     pub image_url: String,
     pub skip_hath_key: String,
-    pub origin_image_url: String,
+    /// `None` if the image shown is already the original.
+    pub origin_image_url_opt: Option<String>,
+    pub filename: String,
+    pub image_size: ImageSize,
+    /// `None` if the image shown is already the original.
+    pub origin_image_size_opt: Option<ImageSize>,
+    /// `None` if the page has no navigation links in the usual shape.
+    pub navigation_opt: Option<PageNavigation>,
 }
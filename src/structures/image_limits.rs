@@ -0,0 +1,24 @@
+/// The image quota of the account, as shown on `home.php`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImageLimits {
+    pub current: u32,
+    pub maximum: u32,
+    /// `None` if the page offers no reset, usually when nothing is used.
+    pub reset_cost_opt: Option<ResetCost>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ResetCost {
+    Gp(u64),
+    Credits(u64),
+}
+
+impl ImageLimits {
+    pub fn remaining(&self) -> u32 {
+        self.maximum.saturating_sub(self.current)
+    }
+
+    pub fn is_exceeded(&self) -> bool {
+        self.current >= self.maximum
+    }
+}
@@ -0,0 +1,6 @@
+use std::time::Duration;
+
+#[derive(Debug, PartialEq)]
+pub struct IpBan {
+    pub expires_in: Duration,
+}
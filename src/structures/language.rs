@@ -0,0 +1,49 @@
+/// The languages E-Hentai files galleries under.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Language {
+    Japanese,
+    English,
+    Chinese,
+    Dutch,
+    French,
+    German,
+    Hungarian,
+    Italian,
+    Korean,
+    Polish,
+    Portuguese,
+    Russian,
+    Spanish,
+    Thai,
+    Vietnamese,
+    /// Galleries without text.
+    NotApplicable,
+    Other,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum LanguageVariant {
+    Original,
+    /// `TR` on the site.
+    Translated,
+    /// `RW` on the site.
+    Rewrite,
+}
+
+/// How far a detected language can be trusted, by where it was found.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub enum Confidence {
+    /// Guessed from the script of the title.
+    Low,
+    /// A marker in the title, such as `[English]`.
+    Medium,
+    /// A language tag, or the Language field of the detail page.
+    High,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LanguageDetection {
+    pub language: Language,
+    pub variant: LanguageVariant,
+    pub confidence: Confidence,
+}
@@ -10,7 +10,7 @@ mod torrent;
 mod vote_comment;
 mod vote_tag;
 mod favorite_slot;
-mod gallery_multi_page_viewer_p_token;
+mod gallery_multi_page_viewer;
 mod gallery_not_available;
 mod gallery_page_api;
 mod gallery_page;
@@ -26,6 +26,10 @@ mod search_nav;
 mod gallery_list;
 mod gallery_detail;
 mod gallery_info;
+mod ip_ban;
+mod language;
+mod parsed_title;
+mod image_limits;
 
 pub use {
     category::Category,
@@ -34,9 +38,9 @@ pub use {
     favorites::Favorite,
     forums::Forums,
     gallery_identity::GalleryIdentity,
-    gallery_multi_page_viewer_p_token::GalleryMultiPageViewerPToken,
-    gallery_not_available::GalleryNotAvailable,
-    gallery_page::GalleryPage,
+    gallery_multi_page_viewer::{GalleryMultiPageViewer, MultiPageViewerDispatch, MultiPageViewerImage},
+    gallery_not_available::{GalleryNotAvailable, GalleryUnavailable},
+    gallery_page::{GalleryPage, ImageSize, PageNavigation},
     gallery_page_api::GalleryPageApi,
     gallery_page_url::GalleryPageUrl,
     gallery_tag_group::GalleryTagGroup,
@@ -44,18 +48,26 @@ pub use {
     profile::Profile,
     rate_gallery::RateGallery,
     rating::Rating,
-    sign_in::SignIn,
     thumb::Thumb,
     torrent::Torrent,
     vote_comment::VoteComment,
     vote_tag::VoteTag,
     search_nav::SearchNav,
     gallery_list::GalleryList,
-    gallery_info::GalleryInfo
+    gallery_info::GalleryInfo,
+    ip_ban::IpBan,
+    parsed_title::ParsedTitle,
+    image_limits::{ImageLimits, ResetCost},
 };
 
 pub use archive::{Archive, ArchiveItem};
 
+pub use sign_in::{SignIn, SignInError};
+
+pub use gallery_api::{GalleryMetadata, GalleryMetadataList};
+
+pub use language::{Confidence, Language, LanguageDetection, LanguageVariant};
+
 pub use gallery_token_api::{GalleryTokenList, GalleryToken};
 
 pub use gallery_detail::{
@@ -66,4 +78,7 @@ pub use gallery_detail::{
     GalleryPreviewLarge,
     GalleryPreviewMedium,
     GalleryPreviewSet,
+    GalleryVersion,
+    HiddenReason,
+    Visible,
 };
@@ -0,0 +1,24 @@
+use crate::structures::Language;
+
+/// A gallery title split by the E-Hentai naming convention.
+///
+/// ```text
+/// (Event) [Circle (Artist)] Title (Parody) [Language] [Translator] [Digital] [Decensored] ch.1-23
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ParsedTitle {
+    pub event_opt: Option<String>,
+    pub circle_opt: Option<String>,
+    /// `[Artist]` alone is an artist, not a circle.
+    pub artist_opt: Option<String>,
+    /// The title with every group and the chapter removed.
+    pub title: String,
+    pub parody_opt: Option<String>,
+    pub language_opt: Option<Language>,
+    pub translator_opt: Option<String>,
+    pub is_digital: bool,
+    pub is_decensored: bool,
+    pub chapter_opt: Option<String>,
+    /// Groups that fit nowhere else, such as `[Ongoing]`.
+    pub extra_vec: Vec<String>,
+}
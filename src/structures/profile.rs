@@ -1,5 +1,16 @@
+use chrono::NaiveDate;
+
 #[derive(Debug, PartialEq)]
 pub struct Profile {
+    /// Filled by `EhClient::get_profile` from the forums `user_link`.
+    pub member_id_opt: Option<u64>,
     pub display_name: String,
+    /// Resolved against `eh_url::URL_FORUMS`.
     pub avatar: String,
+    /// Member group, such as `Members` or `Gold Star Club`.
+    pub group_opt: Option<String>,
+    pub join_date_opt: Option<NaiveDate>,
+    pub post_count_opt: Option<u32>,
+    /// Inner html of the signature block.
+    pub signature_opt: Option<String>,
 }
@@ -1,4 +1,33 @@
 #[derive(Debug, PartialEq)]
 pub struct SignIn {
     pub username: String,
+    /// Filled by `EhClient::sign_in` once the forums confirm the session.
+    pub member_id_opt: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SignInError {
+    WrongPassword,
+    AccountNotFound,
+    /// The forums show a reCAPTCHA, sign in through a browser and import the cookies.
+    CaptchaRequired,
+    TooManyAttempts,
+    ForumDown,
+    /// The login page reported success, but the forums do not recognize the session.
+    SessionNotEstablished,
+    Other(String),
+}
+
+impl std::fmt::Display for SignInError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignInError::WrongPassword => write!(f, "the password is incorrect"),
+            SignInError::AccountNotFound => write!(f, "the account cannot be found"),
+            SignInError::CaptchaRequired => write!(f, "a captcha must be solved to sign in"),
+            SignInError::TooManyAttempts => write!(f, "too many sign in attempts"),
+            SignInError::ForumDown => write!(f, "the forums are down"),
+            SignInError::SessionNotEstablished => write!(f, "the session was not accepted by the forums"),
+            SignInError::Other(s) => write!(f, "sign in failed: {}", s),
+        }
+    }
 }